@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use super::block_log::BlockLog;
+
+/// One execution of a scheduler block, from its first status event to its
+/// last one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlockExecution {
+    id: String,
+    hash: String,
+    telescope: String,
+    start: String,
+    end: String,
+    status: String,
+    is_failed: bool,
+}
+
+impl BlockExecution {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_hash(&self) -> &str {
+        &self.hash
+    }
+
+    pub fn get_telescope(&self) -> &str {
+        &self.telescope
+    }
+
+    pub fn get_start(&self) -> &str {
+        &self.start
+    }
+
+    pub fn get_end(&self) -> &str {
+        &self.end
+    }
+
+    /// Status of the last event of the execution.
+    pub fn get_status(&self) -> &str {
+        &self.status
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.is_failed
+    }
+
+    /// Whether the last event ended the execution, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        self.status == "COMPLETED" || self.is_failed
+    }
+
+    /// Group block status events by block id and hash, ordered by start.
+    pub fn from_block_logs(block_logs: &[BlockLog]) -> Vec<BlockExecution> {
+        let mut executions: HashMap<(&str, &str), BlockExecution> = HashMap::new();
+
+        for block_log in block_logs {
+            let time = block_log.get_date_added();
+            let execution = executions
+                .entry((block_log.get_id(), block_log.get_hash()))
+                .or_insert_with(|| BlockExecution {
+                    id: block_log.get_id().to_owned(),
+                    hash: block_log.get_hash().to_owned(),
+                    telescope: block_log.get_index_label(),
+                    start: time.to_owned(),
+                    ..Default::default()
+                });
+            if time < execution.start.as_str() {
+                execution.start = time.to_owned();
+            }
+            if time >= execution.end.as_str() {
+                execution.end = time.to_owned();
+                execution.status = block_log.get_status().to_owned();
+                execution.is_failed = block_log.is_failed();
+            }
+        }
+
+        let mut executions: Vec<BlockExecution> = executions.into_values().collect();
+        executions.sort_by(|a, b| a.start.cmp(&b.start).then(a.id.cmp(&b.id)));
+        executions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_from_block_logs() {
+        let block_logs: Vec<BlockLog> = serde_json::from_str(
+            r#"[
+                {"time":"2024-08-14T01:10:00Z","id":"BLOCK-T17","status":"COMPLETED","hash":"a","sal_index":2},
+                {"time":"2024-08-14T01:00:00Z","id":"BLOCK-T17","status":"STARTED","hash":"a","sal_index":2},
+                {"time":"2024-08-14T01:20:00Z","id":"BLOCK-T17","status":"STARTED","hash":"b","sal_index":2}
+            ]"#,
+        )
+        .unwrap();
+
+        let executions = BlockExecution::from_block_logs(&block_logs);
+
+        assert_eq!(executions.len(), 2);
+        assert_eq!(executions[0].get_start(), "2024-08-14T01:00:00Z");
+        assert_eq!(executions[0].get_end(), "2024-08-14T01:10:00Z");
+        assert_eq!(executions[0].get_status(), "COMPLETED");
+        assert_eq!(executions[0].get_telescope(), "AuxTel");
+        assert_eq!(executions[1].get_status(), "STARTED");
+        assert!(executions[0].is_finished());
+        assert!(!executions[1].is_finished());
+    }
+}
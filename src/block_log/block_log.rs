@@ -1,13 +1,11 @@
 use askama::Template;
-use chrono::{DateTime, NaiveDateTime};
-use lsst_efd_client::EfdAuth;
-use reqwest::Client;
+use chrono::NaiveDateTime;
 use std::error::Error as StdError;
-use thiserror::Error;
 
-#[derive(Clone, Debug, Eq, Error, PartialEq)]
-#[error("{0}")]
-struct ErrorRetrievingFaultLog(String);
+use crate::{
+    client::client::RolexClient, efd::efd_query::EfdQuery, site::site::Site,
+    timeline::timeline::Filterable,
+};
 
 #[derive(Debug, Deserialize, Serialize, Default, Template)]
 #[template(path = "block_log.html", ext = "html")]
@@ -19,44 +17,27 @@ pub struct BlockLog {
     sal_index: usize,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
-struct QueryResult {
-    results: Vec<Payload>,
-}
-
-#[derive(Debug, Deserialize, Serialize, Default)]
-struct Payload {
-    statement_id: usize,
-    series: Vec<Series>,
-}
+type Row = (String, String, String, String, usize);
 
-#[derive(Debug, Deserialize, Serialize, Default)]
-struct Series {
-    name: String,
-    columns: Vec<String>,
-    values: Vec<(String, String, String, String, usize)>,
-}
-
-impl Series {
-    fn into_fault_log(&self) -> Vec<BlockLog> {
-        self.values
-            .iter()
-            .map(|(time, id, status, hash, sal_index)| BlockLog {
-                time: time.to_string(),
-                id: id.to_string(),
-                status: status.to_string(),
-                hash: hash.to_string(),
-                sal_index: *sal_index,
-            })
-            .collect()
-    }
-}
+const SELECT: &str = r#"SELECT "id", "status", "hash", "salIndex" FROM "efd"."autogen"."lsst.sal.Scheduler.logevent_blockStatus""#;
 
 impl BlockLog {
     pub fn get_date_added(&self) -> &str {
         &self.time
     }
 
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_status(&self) -> &str {
+        &self.status
+    }
+
+    pub fn get_hash(&self) -> &str {
+        &self.hash
+    }
+
     pub fn get_index_label(&self) -> String {
         match self.sal_index {
             1 => "Maintel".to_owned(),
@@ -66,44 +47,56 @@ impl BlockLog {
         }
     }
 
+    /// Block status that ended an execution unsuccessfully.
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self.status.as_str(),
+            "ERROR" | "FAILED" | "INTERRUPTED" | "TERMINATED"
+        )
+    }
+
     pub async fn retrieve(
-        efd_name: &str,
+        client: &RolexClient,
+        site: &Site,
+        date_start: &NaiveDateTime,
+        date_end: &NaiveDateTime,
+    ) -> Result<Vec<BlockLog>, Box<dyn StdError>> {
+        BlockLog::retrieve_with(client, site, &EfdQuery::default(), date_start, date_end).await
+    }
+
+    /// Retrieve block status events over a possibly long window, chunked
+    /// according to `efd_query`.
+    pub async fn retrieve_with(
+        client: &RolexClient,
+        site: &Site,
+        efd_query: &EfdQuery,
         date_start: &NaiveDateTime,
         date_end: &NaiveDateTime,
     ) -> Result<Vec<BlockLog>, Box<dyn StdError>> {
-        let efd_auth = EfdAuth::new(efd_name).await?;
+        let rows: Vec<Row> = efd_query
+            .query(client, site, SELECT, date_start, date_end, |row: &Row| {
+                &row.0
+            })
+            .await?;
 
-        let influxdb_url = format!(
-            "https://{}:{}/influxdb/query",
-            efd_auth.get_host(),
-            efd_auth.get_port(),
-        );
-        // Create a reqwest client
-        let client = Client::new();
+        Ok(rows
+            .into_iter()
+            .map(|(time, id, status, hash, sal_index)| BlockLog {
+                time,
+                id,
+                status,
+                hash,
+                sal_index,
+            })
+            .collect())
+    }
+}
 
-        let query = format!(
-            r#"SELECT "id", "status", "hash", "salIndex" FROM "efd"."autogen"."lsst.sal.Scheduler.logevent_blockStatus" WHERE time > '{date_start}' AND time < '{date_end}'"#
-        );
-        println!("{query}");
-        // Construct the full URL with query parameters
-        let response = client
-            .get(influxdb_url)
-            .basic_auth(efd_auth.get_username(), Some(efd_auth.get_password()))
-            .query(&[("db", "efd"), ("q", &query)])
-            .send()
-            .await?; // Check the status code
-        if response.status().is_success() {
-            // Parse the response JSON
-            let text = response.text().await?;
-            let query_result: QueryResult = serde_json::from_str(&text)?;
-            Ok(query_result.results[0].series[0].into_fault_log())
-        } else {
-            println!("{response:?}");
-            Err(Box::new(ErrorRetrievingFaultLog(format!(
-                "Error: {:?}",
-                response
-            ))))
-        }
-        // println!("{ping:?}");
+impl Filterable for BlockLog {
+    fn get_filter_attributes(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("source", "BlockLog".to_owned()),
+            ("telescope", self.get_index_label()),
+        ]
     }
 }
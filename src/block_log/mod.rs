@@ -1 +1,2 @@
+pub mod block_execution;
 pub mod block_log;
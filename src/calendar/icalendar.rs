@@ -0,0 +1,293 @@
+use chrono::{NaiveDateTime, Utc};
+
+use crate::{
+    block_log::{block_execution::BlockExecution, block_log::BlockLog},
+    night_plan::night_plan::{JiraUser, NightPlan},
+    site::site::Site,
+    timeline::timeline::parse_time,
+};
+
+const PRODUCT_ID: &str = "-//Rubin Observatory//rolex//EN";
+
+/// Longest content line, in octets, before it is folded (RFC 5545).
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Escape a TEXT property value.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn format_date_time(time: &NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Fold a content line into lines of at most `MAX_LINE_LENGTH` octets,
+/// continuation lines starting with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for character in line.chars() {
+        if length + character.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(character);
+        length += character.len_utf8();
+    }
+    folded
+}
+
+/// Calendar event, in UTC.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CalendarEvent {
+    uid: String,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    summary: String,
+    description: String,
+    url: Option<String>,
+    organizer: Option<JiraUser>,
+    categories: Vec<String>,
+}
+
+impl CalendarEvent {
+    pub fn get_uid(&self) -> &str {
+        &self.uid
+    }
+
+    pub fn get_start(&self) -> &NaiveDateTime {
+        &self.start
+    }
+
+    pub fn get_end(&self) -> &NaiveDateTime {
+        &self.end
+    }
+
+    pub fn get_summary(&self) -> &str {
+        &self.summary
+    }
+
+    /// Event for a night plan, from its planned start to its planned end.
+    /// `None` when the planned dates cannot be parsed.
+    pub fn from_night_plan(
+        site: &Site,
+        night_plan: &NightPlan,
+        owner: Option<&JiraUser>,
+    ) -> Option<CalendarEvent> {
+        let url = night_plan.get_web_url(site);
+        let mut description = String::new();
+        if let Some(owner) = owner {
+            description.push_str(&format!("Owner: {}\n", owner.get_display_name()));
+        }
+        description.push_str(&format!("Zephyr: {url}"));
+
+        Some(CalendarEvent {
+            uid: format!(
+                "{}@{}.nightplan.rolex",
+                night_plan.get_key(),
+                site.get_name()
+            ),
+            start: parse_time(night_plan.get_planned_start_date().as_deref()?)?,
+            end: parse_time(night_plan.get_planned_end_date().as_deref()?)?,
+            summary: format!("{} {}", night_plan.get_key(), night_plan.get_name()),
+            description,
+            url: Some(url),
+            organizer: owner.cloned(),
+            categories: vec!["Night plan".to_owned()],
+        })
+    }
+
+    /// Event for a block execution, named after its final status. `None`
+    /// while the execution is unfinished, as it has no end yet.
+    pub fn from_block_execution(site: &Site, execution: &BlockExecution) -> Option<CalendarEvent> {
+        if !execution.is_finished() {
+            return None;
+        }
+        Some(CalendarEvent {
+            uid: format!(
+                "{}-{}@{}.block.rolex",
+                execution.get_id(),
+                execution.get_hash(),
+                site.get_name()
+            ),
+            start: parse_time(execution.get_start())?,
+            end: parse_time(execution.get_end())?,
+            summary: format!("{} {}", execution.get_id(), execution.get_status()),
+            description: format!(
+                "Block {} on {}, last status {}.",
+                execution.get_id(),
+                execution.get_telescope(),
+                execution.get_status()
+            ),
+            url: None,
+            organizer: None,
+            categories: vec!["Block".to_owned(), execution.get_telescope().to_owned()],
+        })
+    }
+
+    fn to_lines(&self, stamp: &NaiveDateTime) -> Vec<String> {
+        let mut lines = vec![
+            "BEGIN:VEVENT".to_owned(),
+            format!("UID:{}", self.uid),
+            format!("DTSTAMP:{}", format_date_time(stamp)),
+            format!("DTSTART:{}", format_date_time(&self.start)),
+            format!("DTEND:{}", format_date_time(&self.end)),
+            format!("SUMMARY:{}", escape(&self.summary)),
+            format!("DESCRIPTION:{}", escape(&self.description)),
+        ];
+        if let Some(url) = &self.url {
+            lines.push(format!("URL:{url}"));
+        }
+        if let Some(organizer) = &self.organizer {
+            if let Some(email_address) = organizer.get_email_address() {
+                lines.push(format!(
+                    "ORGANIZER;CN=\"{}\":mailto:{email_address}",
+                    organizer.get_display_name().replace('"', "")
+                ));
+            }
+        }
+        if !self.categories.is_empty() {
+            let categories: Vec<String> = self
+                .categories
+                .iter()
+                .map(|category| escape(category))
+                .collect();
+            lines.push(format!("CATEGORIES:{}", categories.join(",")));
+        }
+        lines.push("END:VEVENT".to_owned());
+        lines
+    }
+}
+
+/// iCalendar (.ics) document of night plans and block executions, to
+/// overlay observing activity on a calendar.
+#[derive(Clone, Debug, Default)]
+pub struct ICalendar {
+    name: String,
+    events: Vec<CalendarEvent>,
+}
+
+impl ICalendar {
+    pub fn new(name: &str) -> ICalendar {
+        ICalendar {
+            name: name.to_owned(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_night_plan(
+        mut self,
+        site: &Site,
+        night_plan: &NightPlan,
+        owner: Option<&JiraUser>,
+    ) -> ICalendar {
+        self.events
+            .extend(CalendarEvent::from_night_plan(site, night_plan, owner));
+        self
+    }
+
+    /// Add one event per finished block execution found in `block_logs`.
+    pub fn with_block_logs(mut self, site: &Site, block_logs: &[BlockLog]) -> ICalendar {
+        self.events.extend(
+            BlockExecution::from_block_logs(block_logs)
+                .iter()
+                .filter_map(|execution| CalendarEvent::from_block_execution(site, execution)),
+        );
+        self
+    }
+
+    pub fn get_events(&self) -> &[CalendarEvent] {
+        &self.events
+    }
+
+    pub fn to_ics(&self) -> String {
+        let stamp = Utc::now().naive_utc();
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_owned(),
+            "VERSION:2.0".to_owned(),
+            format!("PRODID:{PRODUCT_ID}"),
+            "CALSCALE:GREGORIAN".to_owned(),
+            format!("X-WR-CALNAME:{}", escape(&self.name)),
+        ];
+        for event in &self.events {
+            lines.extend(event.to_lines(&stamp));
+        }
+        lines.push("END:VCALENDAR".to_owned());
+
+        lines
+            .iter()
+            .map(|line| fold(line) + "\r\n")
+            .collect::<String>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_fold_and_escape() {
+        let folded = fold(&"a".repeat(160));
+
+        assert_eq!(
+            folded.split("\r\n").map(str::len).collect::<Vec<usize>>(),
+            vec![75, 75, 12]
+        );
+        assert_eq!(escape("a;b,c\nd"), "a\\;b\\,c\\nd");
+    }
+
+    #[test]
+    fn test_to_ics() {
+        let block_logs: Vec<BlockLog> = serde_json::from_str(
+            r#"[
+                {"time":"2024-08-14T01:00:00Z","id":"BLOCK-T17","status":"STARTED","hash":"a","sal_index":2},
+                {"time":"2024-08-14T01:10:00Z","id":"BLOCK-T17","status":"COMPLETED","hash":"a","sal_index":2},
+                {"time":"2024-08-14T01:20:00Z","id":"BLOCK-T17","status":"STARTED","hash":"b","sal_index":2}
+            ]"#,
+        )
+        .unwrap();
+        let site = Site::summit();
+
+        let ics = ICalendar::new("Summit observing")
+            .with_block_logs(&site, &block_logs)
+            .to_ics();
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(!ics.contains("UID:BLOCK-T17-b@"));
+        assert!(ics.contains("UID:BLOCK-T17-a@summit.block.rolex\r\n"));
+        assert!(ics.contains("DTSTART:20240814T010000Z\r\nDTEND:20240814T011000Z\r\n"));
+        assert!(ics.contains("SUMMARY:BLOCK-T17 COMPLETED\r\n"));
+        assert!(ics.contains("CATEGORIES:Block,AuxTel\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn test_from_night_plan() {
+        let night_plan_json = r#"{"id":1882,"key":"BLOCK-R19","name":"2024-08-13","project":{"id":10251,"self":"https://api.zephyrscale.smartbear.com/v2/projects/10251"},"jiraProjectVersion":null,"status":{"id":4527562,"self":"https://api.zephyrscale.smartbear.com/v2/statuses/4527562"},"folder":null,"description":null,"plannedStartDate":"2024-08-13T22:00:00Z","plannedEndDate":"2024-08-14T10:00:00Z","owner":{"self":"https://rubinobs.atlassian.net/rest/api/2/user?accountId=5fb2f8a4e1","accountId":"5fb2f8a4e1"},"customFields":{},"links":{"self":"https://api.zephyrscale.smartbear.com/v2/testcycles/1882/links","issues":[],"webLinks":[],"testPlans":[]}}"#;
+        let night_plan: NightPlan = serde_json::from_str(night_plan_json).unwrap();
+        let owner: JiraUser = serde_json::from_str(
+            r#"{"accountId":"5fb2f8a4e1","displayName":"Night \"Owl\"","emailAddress":"owl@example.org","active":true,"timeZone":"America/Santiago"}"#,
+        )
+        .unwrap();
+        let site = Site::summit();
+
+        let ics = ICalendar::new("Summit observing")
+            .with_night_plan(&site, &night_plan, Some(&owner))
+            .to_ics();
+        let without_dates = NightPlan::default();
+
+        assert!(ics.contains("UID:BLOCK-R19@summit.nightplan.rolex\r\n"));
+        assert!(ics.contains("DTSTART:20240813T220000Z\r\nDTEND:20240814T100000Z\r\n"));
+        assert!(ics.contains("SUMMARY:BLOCK-R19 2024-08-13\r\n"));
+        assert!(ics.contains("ORGANIZER;CN=\"Night Owl\":mailto:owl@example.org\r\n"));
+        assert!(ics.contains("CATEGORIES:Night plan\r\n"));
+        assert_eq!(
+            CalendarEvent::from_night_plan(&site, &without_dates, None),
+            None
+        );
+    }
+}
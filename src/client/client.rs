@@ -0,0 +1,316 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use lsst_efd_client::EfdAuth;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{error::Error, sync::Arc, time::Duration};
+use thiserror::Error;
+use tokio::{
+    sync::{Mutex, OnceCell},
+    time::sleep,
+};
+
+use super::{retry_policy::RetryPolicy, ttl_cache::TtlCache};
+use crate::credentials::credentials::{
+    ChainCredentialProvider, CredentialProvider, JIRA_CLOUD_API_TOKEN, JIRA_CLOUD_EMAIL,
+    ZEPHYR_API_TOKEN,
+};
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorSendingRequest(String);
+
+/// How long cached EFD credentials are trusted before asking the
+/// credential service again.
+const EFD_AUTH_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Shared state for talking to the remote services.
+///
+/// Owns a single connection-pooled HTTP client and caches EFD credentials
+/// per EFD name, so one instance should be created per run and passed by
+/// reference to every source. Requests should go through
+/// [`RolexClient::send`] so the [`RetryPolicy`] is applied uniformly.
+pub struct RolexClient {
+    http: Client,
+    retry_policy: RetryPolicy,
+    efd_auths: Mutex<TtlCache<Arc<EfdAuth>>>,
+    credentials: Box<dyn CredentialProvider>,
+    zephyr_authorization: OnceCell<String>,
+    jira_authorization: OnceCell<String>,
+}
+
+impl Default for RolexClient {
+    fn default() -> Self {
+        RolexClient::new()
+    }
+}
+
+impl RolexClient {
+    pub fn new() -> RolexClient {
+        let retry_policy = RetryPolicy::default();
+
+        RolexClient {
+            http: RolexClient::build_http(&retry_policy),
+            retry_policy,
+            efd_auths: Mutex::new(TtlCache::new(EFD_AUTH_TTL)),
+            credentials: Box::new(ChainCredentialProvider::default()),
+            zephyr_authorization: OnceCell::new(),
+            jira_authorization: OnceCell::new(),
+        }
+    }
+
+    fn build_http(retry_policy: &RetryPolicy) -> Client {
+        Client::builder()
+            .connect_timeout(retry_policy.get_connect_timeout())
+            .timeout(retry_policy.get_read_timeout())
+            .build()
+            .expect("Failed to build HTTP client")
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> RolexClient {
+        self.http = RolexClient::build_http(&retry_policy);
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_credential_provider(
+        mut self,
+        credentials: Box<dyn CredentialProvider>,
+    ) -> RolexClient {
+        self.credentials = credentials;
+        self.zephyr_authorization = OnceCell::new();
+        self.jira_authorization = OnceCell::new();
+        self
+    }
+
+    pub fn with_efd_auth_ttl(mut self, efd_auth_ttl: Duration) -> RolexClient {
+        self.efd_auths.get_mut().set_ttl(efd_auth_ttl);
+        self
+    }
+
+    pub fn get_http(&self) -> &Client {
+        &self.http
+    }
+
+    pub fn get_retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Send `request`, retrying according to the retry policy.
+    ///
+    /// The last response is returned as is once attempts are exhausted, so
+    /// callers still need to check the status code.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, Box<dyn Error>> {
+        let request = request.build()?;
+        let max_attempts = self.retry_policy.get_max_attempts(request.method());
+        let mut attempt = 1;
+
+        loop {
+            // Requests with a streaming body cannot be cloned, hence retried.
+            let this_request = match request.try_clone() {
+                Some(clone) => clone,
+                None => return Ok(self.http.execute(request).await?),
+            };
+            let can_retry = attempt < max_attempts;
+
+            match self.http.execute(this_request).await {
+                Ok(response)
+                    if can_retry && self.retry_policy.is_retryable_status(response.status()) =>
+                {
+                    let delay = match self.retry_policy.get_retry_after(&response) {
+                        // Retrying earlier than the server asked would only
+                        // be rate limited again.
+                        Some(delay) if delay > self.retry_policy.get_max_backoff() => {
+                            return Ok(response)
+                        }
+                        Some(delay) => delay,
+                        None => self.retry_policy.get_backoff(attempt),
+                    };
+                    sleep(delay).await;
+                }
+                Ok(response) => return Ok(response),
+                Err(error) if can_retry && (error.is_timeout() || error.is_connect()) => {
+                    sleep(self.retry_policy.get_backoff(attempt)).await;
+                }
+                Err(error) => return Err(Box::new(error)),
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Send `request` to a JSON API with the optional `authorization` and
+    /// JSON `body`, parsing the response body, read as `null` when empty.
+    pub async fn send_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        authorization: Option<&str>,
+        body: Option<&Value>,
+    ) -> Result<T, Box<dyn Error>> {
+        let mut request = request.header("Accept", "application/json");
+        if let Some(authorization) = authorization {
+            request = request.header("Authorization", authorization);
+        }
+        if let Some(body) = body {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(serde_json::to_string(body)?);
+        }
+        let response = self.send(request).await?;
+
+        let status = response.status();
+        let response_text = response.text().await?;
+        if !status.is_success() {
+            return Err(Box::new(ErrorSendingRequest(format!(
+                "Error: {status} {response_text}"
+            ))));
+        }
+        if response_text.is_empty() {
+            return Ok(serde_json::from_value(Value::Null)?);
+        }
+
+        Ok(serde_json::from_str(&response_text)?)
+    }
+
+    /// Return credentials for `efd_name`, only calling the credential
+    /// service when nothing is cached or the cached entry expired.
+    ///
+    /// The cache is not locked while the credential service is called, so
+    /// concurrent lookups may fetch the same credentials twice rather than
+    /// wait on each other.
+    pub async fn get_efd_auth(&self, efd_name: &str) -> Result<Arc<EfdAuth>, Box<dyn Error>> {
+        if let Some(efd_auth) = self.efd_auths.lock().await.get(efd_name) {
+            return Ok(efd_auth);
+        }
+
+        let efd_auth = Arc::new(EfdAuth::new(efd_name).await?);
+        self.efd_auths
+            .lock()
+            .await
+            .insert(efd_name, efd_auth.clone());
+
+        Ok(efd_auth)
+    }
+
+    pub fn get_credentials(&self) -> &dyn CredentialProvider {
+        self.credentials.as_ref()
+    }
+
+    /// `Authorization` header value for the Zephyr Scale API.
+    pub async fn get_zephyr_authorization(&self) -> Result<&str, Box<dyn Error>> {
+        let authorization = self
+            .zephyr_authorization
+            .get_or_try_init(|| async {
+                let token = self.credentials.get(ZEPHYR_API_TOKEN)?;
+                Ok::<String, Box<dyn Error>>(format!("Bearer {token}"))
+            })
+            .await?;
+        Ok(authorization)
+    }
+
+    /// `Authorization` header value for the Jira Cloud API, basic auth
+    /// built from the account email and an API token.
+    pub async fn get_jira_authorization(&self) -> Result<&str, Box<dyn Error>> {
+        let authorization = self
+            .jira_authorization
+            .get_or_try_init(|| async {
+                let email = self.credentials.get(JIRA_CLOUD_EMAIL)?;
+                let token = self.credentials.get(JIRA_CLOUD_API_TOKEN)?;
+                Ok::<String, Box<dyn Error>>(RolexClient::basic_authorization(&email, &token))
+            })
+            .await?;
+        Ok(authorization)
+    }
+
+    pub fn basic_authorization(username: &str, password: &str) -> String {
+        format!(
+            "Basic {}",
+            STANDARD.encode(format!("{username}:{password}"))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::credentials::credentials::StaticCredentialProvider;
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[tokio::test]
+    async fn test_authorization() {
+        let client = RolexClient::new().with_credential_provider(Box::new(
+            StaticCredentialProvider::new(&[
+                (ZEPHYR_API_TOKEN, "zephyr-token"),
+                (JIRA_CLOUD_EMAIL, "observer@lsst.org"),
+                (JIRA_CLOUD_API_TOKEN, "jira-token"),
+            ]),
+        ));
+
+        assert_eq!(
+            client.get_zephyr_authorization().await.unwrap(),
+            "Bearer zephyr-token"
+        );
+        assert_eq!(
+            client.get_jira_authorization().await.unwrap(),
+            "Basic b2JzZXJ2ZXJAbHNzdC5vcmc6amlyYS10b2tlbg=="
+        );
+    }
+
+    #[tokio::test]
+    async fn test_long_retry_after_is_not_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 4096];
+            let _ = stream.read(&mut buffer).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 3600\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let client = RolexClient::new();
+
+        let response = client.send(client.get_http().get(url)).await.unwrap();
+        server.await.unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_send_json_empty_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0; 4096];
+            let _ = stream.read(&mut buffer).await.unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buffer).to_string()
+        });
+        let client = RolexClient::new();
+
+        let response: Value = client
+            .send_json(
+                client.get_http().post(url),
+                Some("Basic abc"),
+                Some(&json!({"a": 1})),
+            )
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(response, Value::Null);
+        assert!(request.contains("authorization: Basic abc"));
+        assert!(request.contains("content-type: application/json"));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod client;
+pub mod retry_policy;
+pub mod ttl_cache;
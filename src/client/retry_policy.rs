@@ -0,0 +1,236 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::{header::RETRY_AFTER, Method, Response, StatusCode};
+use std::time::Duration;
+
+/// Timeouts and retry behaviour applied to every remote call.
+///
+/// Only idempotent requests are retried, and only on connection errors,
+/// timeouts or one of `retryable_statuses`. The delay between attempts
+/// grows exponentially with random jitter, unless the server sent a
+/// `Retry-After` header, which takes precedence. A `Retry-After` longer
+/// than `max_backoff` ends the retries rather than being shortened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    max_attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    retryable_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(120),
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retryable_statuses: vec![408, 429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries, only applying the timeouts.
+    pub fn no_retry() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> RetryPolicy {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn with_read_timeout(mut self, read_timeout: Duration) -> RetryPolicy {
+        self.read_timeout = read_timeout;
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> RetryPolicy {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(
+        mut self,
+        initial_backoff: Duration,
+        max_backoff: Duration,
+        multiplier: f64,
+    ) -> RetryPolicy {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> RetryPolicy {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_retryable_statuses(mut self, retryable_statuses: &[u16]) -> RetryPolicy {
+        self.retryable_statuses = retryable_statuses.to_vec();
+        self
+    }
+
+    pub fn get_connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// Timeout for a single attempt. reqwest applies it from the start of
+    /// the request until the body has been read.
+    pub fn get_read_timeout(&self) -> Duration {
+        self.read_timeout
+    }
+
+    /// Number of attempts allowed for a request with `method`.
+    pub fn get_max_attempts(&self, method: &Method) -> usize {
+        if RetryPolicy::is_idempotent(method) {
+            self.max_attempts
+        } else {
+            1
+        }
+    }
+
+    pub fn is_idempotent(method: &Method) -> bool {
+        matches!(
+            *method,
+            Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+        )
+    }
+
+    pub fn get_max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    pub fn is_retryable_status(&self, status: StatusCode) -> bool {
+        self.retryable_statuses.contains(&status.as_u16())
+    }
+
+    /// Delay before retrying after `attempt` (starting at 1) failed,
+    /// without jitter.
+    pub fn get_base_backoff(&self, attempt: usize) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+
+        Duration::from_secs_f64(backoff.min(self.max_backoff.as_secs_f64()))
+    }
+
+    /// Delay before retrying after `attempt` failed, with jitter applied.
+    pub fn get_backoff(&self, attempt: usize) -> Duration {
+        let backoff = self.get_base_backoff(attempt).as_secs_f64();
+        let jitter = if self.jitter > 0.0 {
+            rand::thread_rng().gen_range(-self.jitter..=self.jitter)
+        } else {
+            0.0
+        };
+
+        Duration::from_secs_f64((backoff * (1.0 + jitter)).max(0.0))
+    }
+
+    /// Delay requested by the server through the `Retry-After` header, as
+    /// sent by Zephyr Scale when rate limiting.
+    pub fn get_retry_after(&self, response: &Response) -> Option<Duration> {
+        let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+        RetryPolicy::parse_retry_after(value, Utc::now())
+    }
+
+    /// Parse a `Retry-After` value, either delay-seconds or an HTTP date.
+    pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+        let value = value.trim();
+
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+
+        let date = DateTime::parse_from_rfc2822(value).ok()?;
+        Some(
+            (date.with_timezone(&Utc) - now)
+                .to_std()
+                .unwrap_or(Duration::ZERO),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_base_backoff() {
+        let policy = RetryPolicy::default().with_backoff(
+            Duration::from_secs(1),
+            Duration::from_secs(5),
+            2.0,
+        );
+
+        assert_eq!(policy.get_base_backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.get_base_backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.get_base_backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.get_base_backoff(4), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_backoff_jitter() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_secs(10), Duration::from_secs(60), 2.0)
+            .with_jitter(0.1);
+
+        for _ in 0..100 {
+            let backoff = policy.get_backoff(1);
+            assert!(backoff >= Duration::from_secs(9));
+            assert!(backoff <= Duration::from_secs(11));
+        }
+    }
+
+    #[test]
+    fn test_max_attempts() {
+        let policy = RetryPolicy::default().with_max_attempts(5);
+
+        assert_eq!(policy.get_max_attempts(&Method::GET), 5);
+        assert_eq!(policy.get_max_attempts(&Method::PUT), 5);
+        assert_eq!(policy.get_max_attempts(&Method::POST), 1);
+        assert_eq!(RetryPolicy::no_retry().get_max_attempts(&Method::GET), 1);
+    }
+
+    #[test]
+    fn test_retryable_status() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy.is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(policy.is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!policy.is_retryable_status(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc3339("2024-08-13T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            RetryPolicy::parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            RetryPolicy::parse_retry_after("Tue, 13 Aug 2024 12:00:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            RetryPolicy::parse_retry_after("Tue, 13 Aug 2024 11:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(RetryPolicy::parse_retry_after("soon", now), None);
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Values keyed by name, each trusted for `ttl` after being inserted.
+#[derive(Clone, Debug)]
+pub struct TtlCache<V: Clone> {
+    ttl: Duration,
+    entries: HashMap<String, (V, Instant)>,
+}
+
+impl<V: Clone> TtlCache<V> {
+    pub fn new(ttl: Duration) -> TtlCache<V> {
+        TtlCache {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get_ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl;
+    }
+
+    /// The value cached for `key`, unless missing or expired.
+    pub fn get(&self, key: &str) -> Option<V> {
+        self.entries
+            .get(key)
+            .filter(|(_, inserted_at)| inserted_at.elapsed() < self.ttl)
+            .map(|(value, _)| value.clone())
+    }
+
+    pub fn insert(&mut self, key: &str, value: V) {
+        self.entries.insert(key.to_owned(), (value, Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_expiry() {
+        let mut cache = TtlCache::new(Duration::from_secs(60));
+        cache.insert("usdf_efd", 1);
+
+        assert_eq!(cache.get("usdf_efd"), Some(1));
+        assert_eq!(cache.get("summit_efd"), None);
+
+        cache.set_ttl(Duration::ZERO);
+        assert_eq!(cache.get("usdf_efd"), None);
+    }
+}
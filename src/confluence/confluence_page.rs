@@ -0,0 +1,186 @@
+use serde_json::{json, Value};
+use std::error::Error;
+use url::Url;
+
+use super::storage_format::ConfluenceExport;
+use crate::{client::client::RolexClient, site::site::Site};
+
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+struct Version {
+    number: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+struct PageLinks {
+    #[serde(default)]
+    webui: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+struct SearchResults {
+    results: Vec<ConfluencePage>,
+}
+
+/// Page of the Confluence content REST API.
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct ConfluencePage {
+    id: String,
+    title: String,
+    #[serde(default)]
+    version: Version,
+    #[serde(rename = "_links", default)]
+    links: PageLinks,
+}
+
+impl ConfluencePage {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn get_version(&self) -> usize {
+        self.version.number
+    }
+
+    /// Path of the page in the Confluence web UI, relative to the wiki.
+    pub fn get_web_path(&self) -> &str {
+        &self.links.webui
+    }
+
+    /// Look up the page titled `title` in `space_key`.
+    pub async fn find(
+        client: &RolexClient,
+        site: &Site,
+        space_key: &str,
+        title: &str,
+    ) -> Result<Option<ConfluencePage>, Box<dyn Error>> {
+        let mut url = Url::parse(site.get_confluence_url())?.join("rest/api/content")?;
+        url.query_pairs_mut()
+            .append_pair("spaceKey", space_key)
+            .append_pair("title", title)
+            .append_pair("expand", "version");
+
+        // Confluence Cloud accepts the same Atlassian API token as Jira.
+        let authorization = client.get_jira_authorization().await?;
+        let search_results: SearchResults = client
+            .send_json(client.get_http().get(url), Some(authorization), None)
+            .await?;
+
+        Ok(search_results.results.into_iter().next())
+    }
+
+    /// Create the export as a new page of `space_key`, under the page
+    /// `parent_id` if given.
+    pub async fn create(
+        client: &RolexClient,
+        site: &Site,
+        space_key: &str,
+        parent_id: Option<&str>,
+        export: &ConfluenceExport<'_>,
+    ) -> Result<ConfluencePage, Box<dyn Error>> {
+        let url = Url::parse(site.get_confluence_url())?.join("rest/api/content")?;
+        let payload = ConfluencePage::get_payload(space_key, parent_id, export, None);
+
+        let authorization = client.get_jira_authorization().await?;
+        client
+            .send_json(
+                client.get_http().post(url),
+                Some(authorization),
+                Some(&payload),
+            )
+            .await
+    }
+
+    /// Replace the body of this page with the export.
+    pub async fn update(
+        &self,
+        client: &RolexClient,
+        site: &Site,
+        space_key: &str,
+        export: &ConfluenceExport<'_>,
+    ) -> Result<ConfluencePage, Box<dyn Error>> {
+        let url = Url::parse(site.get_confluence_url())?
+            .join(&format!("rest/api/content/{}", self.id))?;
+        let payload =
+            ConfluencePage::get_payload(space_key, None, export, Some(self.version.number + 1));
+
+        let authorization = client.get_jira_authorization().await?;
+        client
+            .send_json(
+                client.get_http().put(url),
+                Some(authorization),
+                Some(&payload),
+            )
+            .await
+    }
+
+    /// Update the page titled like the export, creating it if missing.
+    pub async fn publish(
+        client: &RolexClient,
+        site: &Site,
+        space_key: &str,
+        parent_id: Option<&str>,
+        export: &ConfluenceExport<'_>,
+    ) -> Result<ConfluencePage, Box<dyn Error>> {
+        match ConfluencePage::find(client, site, space_key, &export.get_title()).await? {
+            Some(page) => page.update(client, site, space_key, export).await,
+            None => ConfluencePage::create(client, site, space_key, parent_id, export).await,
+        }
+    }
+
+    fn get_payload(
+        space_key: &str,
+        parent_id: Option<&str>,
+        export: &ConfluenceExport<'_>,
+        version: Option<usize>,
+    ) -> Value {
+        let mut payload = json!({
+            "type": "page",
+            "title": export.get_title(),
+            "space": {"key": space_key},
+            "body": {
+                "storage": {
+                    "value": export.to_storage_format(),
+                    "representation": "storage",
+                },
+            },
+        });
+        if let Some(parent_id) = parent_id {
+            payload["ancestors"] = json!([{ "id": parent_id }]);
+        }
+        if let Some(version) = version {
+            payload["version"] = json!({ "number": version });
+        }
+        payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::night_fetcher::night_fetcher::NightData;
+
+    #[test]
+    fn test_get_payload() {
+        let site = Site::summit();
+        let night_data = NightData::default();
+        let export = ConfluenceExport::new(&site).with_night(20240813, &night_data);
+
+        let payload = ConfluencePage::get_payload("OBS", Some("1234"), &export, Some(3));
+        let page: ConfluencePage = serde_json::from_str(
+            r#"{"id":"5678","type":"page","title":"Night report 20240813 - summit","version":{"number":2},"_links":{"webui":"/spaces/OBS/pages/5678"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(payload["title"], "Night report 20240813 - summit");
+        assert_eq!(payload["ancestors"][0]["id"], "1234");
+        assert_eq!(payload["version"]["number"], 3);
+        assert_eq!(payload["body"]["storage"]["representation"], "storage");
+        assert_eq!(page.get_version(), 2);
+        assert_eq!(page.get_web_path(), "/spaces/OBS/pages/5678");
+    }
+}
@@ -0,0 +1,2 @@
+pub mod confluence_page;
+pub mod storage_format;
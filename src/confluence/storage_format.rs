@@ -0,0 +1,274 @@
+use crate::{
+    block_log::block_execution::BlockExecution,
+    fault_log::alarm_episode::AlarmEpisode,
+    night_fetcher::night_fetcher::{NightData, Source},
+    site::site::Site,
+    timeline::timeline::format_time,
+};
+
+/// Escape text for Confluence storage format, which is XHTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Status lozenge macro. Colours are the ones Confluence accepts: Grey,
+/// Red, Yellow, Green, Blue and Purple.
+pub fn status_macro(colour: &str, title: &str) -> String {
+    format!(
+        r#"<ac:structured-macro ac:name="status"><ac:parameter ac:name="colour">{colour}</ac:parameter><ac:parameter ac:name="title">{}</ac:parameter></ac:structured-macro>"#,
+        escape(title)
+    )
+}
+
+/// Jira issue macro, rendered by Confluence with the live issue status.
+pub fn jira_macro(issue_key: &str) -> String {
+    format!(
+        r#"<ac:structured-macro ac:name="jira"><ac:parameter ac:name="key">{}</ac:parameter></ac:structured-macro>"#,
+        escape(issue_key)
+    )
+}
+
+fn block_status_macro(execution: &BlockExecution) -> String {
+    let colour = match execution.get_status() {
+        "COMPLETED" => "Green",
+        "INTERRUPTED" => "Yellow",
+        _ if execution.is_failed() => "Red",
+        _ => "Blue",
+    };
+    status_macro(colour, execution.get_status())
+}
+
+fn severity_macro(severity: usize) -> String {
+    match severity {
+        0 | 1 => status_macro("Grey", "NONE"),
+        2 => status_macro("Yellow", "WARNING"),
+        3 => status_macro("Red", "SERIOUS"),
+        _ => status_macro("Red", "CRITICAL"),
+    }
+}
+
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut table = String::from("<table><tbody><tr>");
+    for header in headers {
+        table.push_str(&format!("<th>{header}</th>"));
+    }
+    table.push_str("</tr>");
+    for cells in rows {
+        table.push_str("<tr>");
+        for cell in cells {
+            table.push_str(&format!("<td>{cell}</td>"));
+        }
+        table.push_str("</tr>");
+    }
+    table.push_str("</tbody></table>");
+    table
+}
+
+/// Night report of one night or a range of nights in Confluence storage
+/// format, ready to be used as the body of a page.
+#[derive(Debug)]
+pub struct ConfluenceExport<'a> {
+    site: &'a Site,
+    nights: Vec<(usize, &'a NightData)>,
+}
+
+impl<'a> ConfluenceExport<'a> {
+    pub fn new(site: &'a Site) -> ConfluenceExport<'a> {
+        ConfluenceExport {
+            site,
+            nights: Vec::new(),
+        }
+    }
+
+    pub fn with_night(mut self, day_obs: usize, night_data: &'a NightData) -> ConfluenceExport<'a> {
+        self.nights.push((day_obs, night_data));
+        self.nights.sort_by_key(|(day_obs, _)| *day_obs);
+        self
+    }
+
+    /// Page title covering every night of the export.
+    pub fn get_title(&self) -> String {
+        let site = self.site.get_name();
+        match (self.nights.first(), self.nights.last()) {
+            (Some((first, _)), Some((last, _))) if first != last => {
+                format!("Night reports {first} - {last} - {site}")
+            }
+            (Some((day_obs, _)), _) => format!("Night report {day_obs} - {site}"),
+            _ => format!("Night reports - {site}"),
+        }
+    }
+
+    pub fn to_storage_format(&self) -> String {
+        let mut storage = String::new();
+        for (day_obs, night_data) in &self.nights {
+            storage.push_str(&self.format_night(*day_obs, night_data));
+        }
+        storage
+    }
+
+    fn format_issue_keys(&self, issue_keys: &[String]) -> String {
+        issue_keys
+            .iter()
+            .map(|issue_key| jira_macro(issue_key))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    fn format_night(&self, day_obs: usize, night_data: &NightData) -> String {
+        let projects = self.site.get_jira_projects();
+        let mut storage = format!("<h1>Night {day_obs}</h1>");
+        if let Some(night_plan) = night_data.get_night_plan() {
+            storage.push_str(&format!(
+                "<p>Night plan: {} - {}</p>",
+                escape(night_plan.get_key()),
+                escape(night_plan.get_name())
+            ));
+        }
+
+        storage.push_str("<h2>Narrative log</h2>");
+        storage.push_str(&format!(
+            "<p><em>{}</em></p>",
+            escape(&night_data.describe_status(Source::NarrativeLog))
+        ));
+        if !night_data.get_narrative_logs().is_empty() {
+            storage.push_str("<ul>");
+            for narrative_log in night_data.get_narrative_logs() {
+                let message =
+                    escape(&narrative_log.get_message_plain_text()).replace('\n', "<br />");
+                let mut item = format!(
+                    "<strong>{}</strong> {}: {message}",
+                    format_time(narrative_log.get_date_added()),
+                    escape(narrative_log.get_user_id())
+                );
+                if narrative_log.get_time_lost() > 0.0 {
+                    item.push_str(&format!(
+                        " <em>({:.2} h lost)</em>",
+                        narrative_log.get_time_lost()
+                    ));
+                }
+                let issue_keys = narrative_log.get_issue_keys(projects);
+                if !issue_keys.is_empty() {
+                    item.push(' ');
+                    item.push_str(&self.format_issue_keys(&issue_keys));
+                }
+                storage.push_str(&format!("<li>{item}</li>"));
+            }
+            storage.push_str("</ul>");
+        }
+
+        storage.push_str("<h2>Exposure log</h2>");
+        storage.push_str(&format!(
+            "<p><em>{}</em></p>",
+            escape(&night_data.describe_status(Source::ExposureLog))
+        ));
+        let rows: Vec<Vec<String>> = night_data
+            .get_exposure_logs()
+            .iter()
+            .map(|exposure_log| {
+                vec![
+                    escape(exposure_log.get_obs_id()),
+                    escape(exposure_log.get_exposure_flag()),
+                    escape(exposure_log.get_user_id()),
+                    escape(&exposure_log.get_message_plain_text()).replace('\n', "<br />"),
+                    self.format_issue_keys(&exposure_log.get_issue_keys(projects)),
+                ]
+            })
+            .collect();
+        if !rows.is_empty() {
+            storage.push_str(&table(
+                &["Obs id", "Flag", "User", "Message", "Issues"],
+                &rows,
+            ));
+        }
+
+        storage.push_str("<h2>Alarms</h2>");
+        storage.push_str(&format!(
+            "<p><em>{}</em></p>",
+            escape(&night_data.describe_status(Source::FaultLog))
+        ));
+        let rows: Vec<Vec<String>> = AlarmEpisode::from_fault_logs(night_data.get_fault_logs())
+            .iter()
+            .map(|episode| {
+                vec![
+                    format_time(episode.get_start()),
+                    episode
+                        .get_end()
+                        .as_deref()
+                        .map_or("ongoing".to_owned(), format_time),
+                    escape(episode.get_name()),
+                    severity_macro(episode.get_max_severity()),
+                    episode.get_count().to_string(),
+                    escape(episode.get_reason()),
+                ]
+            })
+            .collect();
+        if !rows.is_empty() {
+            storage.push_str(&table(
+                &["Start", "End", "Alarm", "Severity", "Events", "Reason"],
+                &rows,
+            ));
+        }
+
+        storage.push_str("<h2>Blocks</h2>");
+        storage.push_str(&format!(
+            "<p><em>{}</em></p>",
+            escape(&night_data.describe_status(Source::BlockLog))
+        ));
+        let rows: Vec<Vec<String>> = BlockExecution::from_block_logs(night_data.get_block_logs())
+            .iter()
+            .map(|execution| {
+                vec![
+                    format_time(execution.get_end()),
+                    escape(execution.get_telescope()),
+                    escape(execution.get_id()),
+                    block_status_macro(execution),
+                ]
+            })
+            .collect();
+        if !rows.is_empty() {
+            storage.push_str(&table(&["Time", "Telescope", "Block", "Status"], &rows));
+        }
+
+        storage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_macros() {
+        assert_eq!(
+            status_macro("Green", "A&B"),
+            r#"<ac:structured-macro ac:name="status"><ac:parameter ac:name="colour">Green</ac:parameter><ac:parameter ac:name="title">A&amp;B</ac:parameter></ac:structured-macro>"#
+        );
+        assert!(
+            jira_macro("OBS-123").contains(r#"<ac:parameter ac:name="key">OBS-123</ac:parameter>"#)
+        );
+        assert!(severity_macro(3).contains("SERIOUS"));
+    }
+
+    #[test]
+    fn test_range_of_nights() {
+        let site = Site::summit();
+        let night_data = NightData::default();
+
+        let export = ConfluenceExport::new(&site)
+            .with_night(20240814, &night_data)
+            .with_night(20240813, &night_data);
+        let storage = export.to_storage_format();
+
+        assert_eq!(
+            export.get_title(),
+            "Night reports 20240813 - 20240814 - summit"
+        );
+        assert!(storage.starts_with("<h1>Night 20240813</h1>"));
+        assert!(storage.contains("<h1>Night 20240814</h1>"));
+        assert!(storage.contains("<p><em>not fetched</em></p>"));
+    }
+}
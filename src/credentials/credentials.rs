@@ -0,0 +1,163 @@
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorMissingCredential(String);
+
+pub const ZEPHYR_API_TOKEN: &str = "ZEPHYR_API_TOKEN";
+pub const JIRA_CLOUD_EMAIL: &str = "JIRA_CLOUD_EMAIL";
+pub const JIRA_CLOUD_API_TOKEN: &str = "JIRA_CLOUD_API_TOKEN";
+pub const SLACK_WEBHOOK_URL: &str = "SLACK_WEBHOOK_URL";
+
+/// Source of secrets such as API tokens, looked up by name.
+pub trait CredentialProvider: Send + Sync {
+    fn get(&self, name: &str) -> Result<String, Box<dyn Error>>;
+}
+
+/// Reads credentials from environment variables of the same name.
+#[derive(Clone, Debug, Default)]
+pub struct EnvCredentialProvider;
+
+impl CredentialProvider for EnvCredentialProvider {
+    fn get(&self, name: &str) -> Result<String, Box<dyn Error>> {
+        env::var(name).map_err(|_| {
+            Box::new(ErrorMissingCredential(format!(
+                "Environment variable {name} is not set."
+            ))) as Box<dyn Error>
+        })
+    }
+}
+
+/// Reads credentials from a directory holding one file per secret, named
+/// after the credential in lower case (e.g. `~/.lsst/zephyr_api_token`).
+#[derive(Clone, Debug)]
+pub struct FileCredentialProvider {
+    directory: PathBuf,
+}
+
+impl Default for FileCredentialProvider {
+    fn default() -> Self {
+        let home = env::var("HOME").unwrap_or_default();
+        FileCredentialProvider::new(&Path::new(&home).join(".lsst"))
+    }
+}
+
+impl FileCredentialProvider {
+    pub fn new(directory: &Path) -> FileCredentialProvider {
+        FileCredentialProvider {
+            directory: directory.to_owned(),
+        }
+    }
+}
+
+impl CredentialProvider for FileCredentialProvider {
+    fn get(&self, name: &str) -> Result<String, Box<dyn Error>> {
+        let path = self.directory.join(name.to_lowercase());
+        let secret = fs::read_to_string(&path).map_err(|error| {
+            Box::new(ErrorMissingCredential(format!(
+                "Cannot read {}: {error}",
+                path.display()
+            ))) as Box<dyn Error>
+        })?;
+
+        Ok(secret.trim().to_owned())
+    }
+}
+
+/// Fixed in-memory credentials, mostly for tests.
+#[derive(Clone, Debug, Default)]
+pub struct StaticCredentialProvider {
+    credentials: HashMap<String, String>,
+}
+
+impl StaticCredentialProvider {
+    pub fn new(credentials: &[(&str, &str)]) -> StaticCredentialProvider {
+        StaticCredentialProvider {
+            credentials: credentials
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl CredentialProvider for StaticCredentialProvider {
+    fn get(&self, name: &str) -> Result<String, Box<dyn Error>> {
+        self.credentials.get(name).cloned().ok_or_else(|| {
+            Box::new(ErrorMissingCredential(format!(
+                "No credential named {name}."
+            ))) as Box<dyn Error>
+        })
+    }
+}
+
+/// Tries each provider in turn, returning the first credential found.
+pub struct ChainCredentialProvider {
+    providers: Vec<Box<dyn CredentialProvider>>,
+}
+
+impl Default for ChainCredentialProvider {
+    /// Environment variables first, then the `~/.lsst` secrets directory.
+    fn default() -> Self {
+        ChainCredentialProvider::new(vec![
+            Box::new(EnvCredentialProvider),
+            Box::new(FileCredentialProvider::default()),
+        ])
+    }
+}
+
+impl ChainCredentialProvider {
+    pub fn new(providers: Vec<Box<dyn CredentialProvider>>) -> ChainCredentialProvider {
+        ChainCredentialProvider { providers }
+    }
+}
+
+impl CredentialProvider for ChainCredentialProvider {
+    fn get(&self, name: &str) -> Result<String, Box<dyn Error>> {
+        let mut errors = Vec::new();
+        for provider in &self.providers {
+            match provider.get(name) {
+                Ok(credential) => return Ok(credential),
+                Err(error) => errors.push(error.to_string()),
+            }
+        }
+        Err(Box::new(ErrorMissingCredential(format!(
+            "Credential {name} not found: {}",
+            errors.join(" ")
+        ))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_chain() {
+        let directory = env::temp_dir().join(format!("rolex-credentials-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("jira_cloud_api_token"), "file-token\n").unwrap();
+
+        let provider = ChainCredentialProvider::new(vec![
+            Box::new(StaticCredentialProvider::new(&[(
+                ZEPHYR_API_TOKEN,
+                "static-token",
+            )])),
+            Box::new(FileCredentialProvider::new(&directory)),
+        ]);
+
+        assert_eq!(provider.get(ZEPHYR_API_TOKEN).unwrap(), "static-token");
+        assert_eq!(provider.get(JIRA_CLOUD_API_TOKEN).unwrap(), "file-token");
+        assert!(provider.get(JIRA_CLOUD_EMAIL).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+use chrono::{Duration, NaiveDateTime};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use thiserror::Error;
+
+use crate::{client::client::RolexClient, site::site::Site};
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorQueryingEfd(String);
+
+#[derive(Debug, Deserialize, Serialize)]
+struct QueryResult<V> {
+    results: Vec<Payload<V>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Payload<V> {
+    statement_id: usize,
+    #[serde(default = "Vec::new")]
+    series: Vec<Series<V>>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Series<V> {
+    name: String,
+    columns: Vec<String>,
+    values: Vec<V>,
+}
+
+/// Runs an InfluxDB query against the EFD over a time window.
+///
+/// Long windows are split into chunks of `chunk_size` that are queried
+/// with at most `max_concurrency` requests in flight, and the rows are
+/// merged back in time order. Chunk edges are inclusive, so rows sitting
+/// exactly on a boundary are returned by both neighbours and dropped from
+/// the later one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EfdQuery {
+    chunk_size: Duration,
+    max_concurrency: usize,
+    chunked_response: bool,
+    chunk_rows: usize,
+}
+
+impl Default for EfdQuery {
+    fn default() -> Self {
+        EfdQuery {
+            chunk_size: Duration::days(1),
+            max_concurrency: 2,
+            chunked_response: false,
+            chunk_rows: 10000,
+        }
+    }
+}
+
+impl EfdQuery {
+    pub fn with_chunk_size(mut self, chunk_size: Duration) -> EfdQuery {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> EfdQuery {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Ask InfluxDB for a `chunked=true` response, streamed back in
+    /// batches of `chunk_rows` rows.
+    pub fn with_chunked_response(mut self, chunk_rows: usize) -> EfdQuery {
+        self.chunked_response = true;
+        self.chunk_rows = chunk_rows.max(1);
+        self
+    }
+
+    /// Split `[date_start, date_end]` into consecutive windows no longer
+    /// than the chunk size.
+    pub fn split_window(
+        &self,
+        date_start: &NaiveDateTime,
+        date_end: &NaiveDateTime,
+    ) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+        if self.chunk_size <= Duration::zero() {
+            return vec![(*date_start, *date_end)];
+        }
+
+        let mut windows = Vec::new();
+        let mut chunk_start = *date_start;
+
+        while chunk_start < *date_end {
+            let chunk_end = (chunk_start + self.chunk_size).min(*date_end);
+            windows.push((chunk_start, chunk_end));
+            chunk_start = chunk_end;
+        }
+        windows
+    }
+
+    /// Run `select` (a `SELECT ... FROM ...` statement without a `WHERE`
+    /// clause) over the window, returning one `V` per row.
+    pub async fn query<V>(
+        &self,
+        client: &RolexClient,
+        site: &Site,
+        select: &str,
+        date_start: &NaiveDateTime,
+        date_end: &NaiveDateTime,
+        get_time: fn(&V) -> &str,
+    ) -> Result<Vec<V>, Box<dyn Error>>
+    where
+        V: DeserializeOwned + PartialEq,
+    {
+        let chunks: Vec<Vec<V>> = stream::iter(self.split_window(date_start, date_end))
+            .map(|(chunk_start, chunk_end)| {
+                let query =
+                    format!("{select} WHERE time >= '{chunk_start}' AND time <= '{chunk_end}'");
+                async move { self.query_chunk(client, site, &query).await }
+            })
+            .buffered(self.max_concurrency)
+            .try_collect()
+            .await?;
+
+        Ok(EfdQuery::merge_chunks(chunks, get_time))
+    }
+
+    async fn query_chunk<V>(
+        &self,
+        client: &RolexClient,
+        site: &Site,
+        query: &str,
+    ) -> Result<Vec<V>, Box<dyn Error>>
+    where
+        V: DeserializeOwned,
+    {
+        let efd_auth = client.get_efd_auth(site.get_efd_name()).await?;
+
+        let influxdb_url = format!(
+            "https://{}:{}/influxdb/query",
+            efd_auth.get_host(),
+            efd_auth.get_port(),
+        );
+
+        let chunk_rows = self.chunk_rows.to_string();
+        let mut params = vec![("db", "efd"), ("q", query)];
+        if self.chunked_response {
+            params.extend([("chunked", "true"), ("chunk_size", &chunk_rows)]);
+        }
+
+        let request = client
+            .get_http()
+            .get(influxdb_url)
+            .basic_auth(efd_auth.get_username(), Some(efd_auth.get_password()))
+            .query(&params);
+        let mut response = client.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(Box::new(ErrorQueryingEfd(format!("Error: {:?}", response))));
+        }
+
+        let mut rows = Vec::new();
+        if self.chunked_response {
+            // Chunked responses are one JSON document per line, parse them
+            // as they arrive instead of buffering the whole body.
+            let mut buffer: Vec<u8> = Vec::new();
+            while let Some(bytes) = response.chunk().await? {
+                buffer.extend_from_slice(&bytes);
+                while let Some(newline) = buffer.iter().position(|byte| *byte == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=newline).collect();
+                    EfdQuery::parse_document(&line, &mut rows)?;
+                }
+            }
+            EfdQuery::parse_document(&buffer, &mut rows)?;
+        } else {
+            let bytes = response.bytes().await?;
+            EfdQuery::parse_document(&bytes, &mut rows)?;
+        }
+        Ok(rows)
+    }
+
+    fn parse_document<V>(document: &[u8], rows: &mut Vec<V>) -> Result<(), Box<dyn Error>>
+    where
+        V: DeserializeOwned,
+    {
+        if document.iter().all(|byte| byte.is_ascii_whitespace()) {
+            return Ok(());
+        }
+
+        let query_result: QueryResult<V> = serde_json::from_slice(document)?;
+        for payload in query_result.results {
+            if let Some(error) = payload.error {
+                return Err(Box::new(ErrorQueryingEfd(error)));
+            }
+            for series in payload.series {
+                rows.extend(series.values);
+            }
+        }
+        Ok(())
+    }
+
+    /// Concatenate chunk results, dropping rows of a chunk that already
+    /// appeared with the same timestamp at the end of the previous ones.
+    pub fn merge_chunks<V: PartialEq>(chunks: Vec<Vec<V>>, get_time: fn(&V) -> &str) -> Vec<V> {
+        let mut merged: Vec<V> = Vec::new();
+
+        for chunk in chunks {
+            let previous_len = merged.len();
+            for row in chunk {
+                let is_duplicate = merged[..previous_len]
+                    .iter()
+                    .rev()
+                    .take_while(|previous| get_time(previous) == get_time(&row))
+                    .any(|previous| *previous == row);
+                if !is_duplicate {
+                    merged.push(row);
+                }
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_split_window() {
+        let parse_from_str = NaiveDateTime::parse_from_str;
+        let date_start = parse_from_str("2024-08-01T12:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+        let date_end = parse_from_str("2024-08-03T00:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        let windows = EfdQuery::default().split_window(&date_start, &date_end);
+
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].0, date_start);
+        assert_eq!(windows[0].1, date_start + Duration::days(1));
+        assert_eq!(windows[1].0, windows[0].1);
+        assert_eq!(windows[1].1, date_end);
+    }
+
+    #[test]
+    fn test_merge_chunks() {
+        let chunks = vec![
+            vec![
+                ("2024-08-01T00:00:00Z".to_owned(), 1),
+                ("2024-08-02T00:00:00Z".to_owned(), 2),
+            ],
+            vec![
+                ("2024-08-02T00:00:00Z".to_owned(), 2),
+                ("2024-08-02T00:00:00Z".to_owned(), 3),
+                ("2024-08-02T12:00:00Z".to_owned(), 4),
+            ],
+        ];
+
+        let merged = EfdQuery::merge_chunks(chunks, |(time, _)| time);
+
+        let values: Vec<usize> = merged.iter().map(|(_, value)| *value).collect();
+        assert_eq!(values, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_parse_document() {
+        let document = br#"{"results":[{"statement_id":0,"series":[{"name":"lsst.sal.Watcher.logevent_alarm","columns":["time","name","reason","severity"],"values":[["2024-08-13T12:00:01Z","Enabled.ATDome","Fault",3]]}]}]}"#;
+        let empty = br#"{"results":[{"statement_id":0}]}"#;
+
+        let mut rows: Vec<(String, String, String, usize)> = Vec::new();
+        EfdQuery::parse_document(document, &mut rows).unwrap();
+        EfdQuery::parse_document(empty, &mut rows).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].1, "Enabled.ATDome");
+        assert_eq!(rows[0].3, 3);
+    }
+}
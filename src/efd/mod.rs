@@ -0,0 +1 @@
+pub mod efd_query;
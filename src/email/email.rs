@@ -0,0 +1,395 @@
+use askama::Template;
+use lettre::{
+    message::{header::ContentType, Attachment, Mailbox, MultiPart, SinglePart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::error::Error;
+use thiserror::Error;
+
+use crate::{
+    client::client::RolexClient, night_fetcher::night_fetcher::NightData,
+    night_report_page::night_report_page::NightReportPage,
+    night_summary::night_summary::NightSummary, site::site::Site,
+};
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorSendingEmail(String);
+
+/// Width the plain-text part is wrapped to.
+const TEXT_WIDTH: usize = 78;
+
+/// Largest image embedded in the email, larger ones stay linked.
+const MAX_IMAGE_SIZE: usize = 5 * 1024 * 1024;
+
+/// How the connection to the SMTP server is secured.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SmtpSecurity {
+    /// Unencrypted, for local relays and test sinks only.
+    Plain,
+    /// Upgrade the connection with STARTTLS, usually on port 587.
+    #[default]
+    StartTls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+}
+
+/// Image embedded in the email and referenced from the HTML as `cid:`.
+#[derive(Clone, Debug)]
+struct InlineImage {
+    content_id: String,
+    content_type: String,
+    data: Vec<u8>,
+}
+
+/// Night report as a multipart email: the plain-text summary and the
+/// rendered HTML page, with the attached images linked or embedded.
+#[derive(Clone, Debug)]
+pub struct NightReportEmail {
+    subject: String,
+    text: String,
+    html: String,
+    image_urls: Vec<String>,
+    inline_images: Vec<InlineImage>,
+}
+
+impl NightReportEmail {
+    pub fn from_night_data(
+        site: &Site,
+        day_obs: usize,
+        night_data: &NightData,
+    ) -> Result<NightReportEmail, Box<dyn Error>> {
+        let text = NightSummary::new(site, day_obs, night_data)
+            .with_width(TEXT_WIDTH)
+            .to_text();
+        let html = NightReportPage::from_night_data(site, day_obs, night_data)?.render()?;
+
+        let mut image_urls: Vec<String> = Vec::new();
+        let images = night_data
+            .get_narrative_logs()
+            .iter()
+            .flat_map(|narrative_log| narrative_log.get_attached_images())
+            .chain(
+                night_data
+                    .get_exposure_logs()
+                    .iter()
+                    .flat_map(|exposure_log| exposure_log.get_attached_images()),
+            );
+        for url in images {
+            if !image_urls.contains(&url) {
+                image_urls.push(url);
+            }
+        }
+
+        Ok(NightReportEmail {
+            subject: format!("Night report {day_obs} - {}", site.get_name()),
+            text,
+            html,
+            image_urls,
+            inline_images: Vec::new(),
+        })
+    }
+
+    pub fn get_subject(&self) -> &str {
+        &self.subject
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn get_html(&self) -> &str {
+        &self.html
+    }
+
+    /// Urls of the images attached to the log entries of the night.
+    pub fn get_image_urls(&self) -> &[String] {
+        &self.image_urls
+    }
+
+    /// Download the attached images and embed them in the email, pointing
+    /// the HTML at the embedded copies. Images that cannot be downloaded,
+    /// are not served as images or are larger than `MAX_IMAGE_SIZE` stay
+    /// linked. Returns the number of embedded images.
+    pub async fn embed_images(&mut self, client: &RolexClient) -> Result<usize, Box<dyn Error>> {
+        for (index, url) in self.image_urls.iter().enumerate() {
+            let Ok(response) = client.send(client.get_http().get(url)).await else {
+                continue;
+            };
+            if !response.status().is_success()
+                || response
+                    .content_length()
+                    .is_some_and(|length| length as usize > MAX_IMAGE_SIZE)
+            {
+                continue;
+            }
+            let Some(content_type) = response
+                .headers()
+                .get("Content-Type")
+                .and_then(|content_type| content_type.to_str().ok())
+                .filter(|content_type| content_type.starts_with("image/"))
+                .map(str::to_owned)
+            else {
+                continue;
+            };
+            let Ok(data) = response.bytes().await else {
+                continue;
+            };
+            if data.len() > MAX_IMAGE_SIZE {
+                continue;
+            }
+            let data = data.to_vec();
+
+            let content_id = format!("image{index}");
+            let escaped_url = askama::filters::escape(askama::Html, url)?.to_string();
+            self.html = self
+                .html
+                .replace(&escaped_url, &format!("cid:{content_id}"));
+            self.inline_images.push(InlineImage {
+                content_id,
+                content_type,
+                data,
+            });
+        }
+        Ok(self.inline_images.len())
+    }
+
+    pub fn to_message(&self, from: &str, recipients: &[String]) -> Result<Message, Box<dyn Error>> {
+        if recipients.is_empty() {
+            return Err(Box::new(ErrorSendingEmail(
+                "No email recipients configured.".to_owned(),
+            )));
+        }
+
+        let mut builder = Message::builder()
+            .from(from.parse::<Mailbox>()?)
+            .subject(&self.subject);
+        for recipient in recipients {
+            builder = builder.to(recipient.parse::<Mailbox>()?);
+        }
+
+        let mut html = MultiPart::related().singlepart(SinglePart::html(self.html.to_owned()));
+        for image in &self.inline_images {
+            html = html.singlepart(
+                Attachment::new_inline(image.content_id.to_owned())
+                    .body(image.data.clone(), ContentType::parse(&image.content_type)?),
+            );
+        }
+        let body = MultiPart::alternative()
+            .singlepart(SinglePart::plain(self.text.to_owned()))
+            .multipart(html);
+
+        Ok(builder.multipart(body)?)
+    }
+}
+
+/// SMTP server the night report is sent through.
+#[derive(Clone, Debug)]
+pub struct SmtpMailer {
+    host: String,
+    port: u16,
+    security: SmtpSecurity,
+    from: String,
+    credentials: Option<Credentials>,
+}
+
+impl SmtpMailer {
+    /// Mailer using STARTTLS on the submission port.
+    pub fn new(host: &str, from: &str) -> SmtpMailer {
+        SmtpMailer {
+            host: host.to_owned(),
+            port: 587,
+            security: SmtpSecurity::default(),
+            from: from.to_owned(),
+            credentials: None,
+        }
+    }
+
+    pub fn with_port(mut self, port: u16) -> SmtpMailer {
+        self.port = port;
+        self
+    }
+
+    pub fn with_security(mut self, security: SmtpSecurity) -> SmtpMailer {
+        self.security = security;
+        self
+    }
+
+    pub fn with_credentials(mut self, username: &str, password: &str) -> SmtpMailer {
+        self.credentials = Some(Credentials::new(username.to_owned(), password.to_owned()));
+        self
+    }
+
+    pub fn get_host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn get_port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn get_security(&self) -> SmtpSecurity {
+        self.security
+    }
+
+    /// Send `email` to the recipients of `site`, returning how many there
+    /// were.
+    pub async fn send(
+        &self,
+        site: &Site,
+        email: &NightReportEmail,
+    ) -> Result<usize, Box<dyn Error>> {
+        let recipients = site.get_email_recipients();
+        let message = email.to_message(&self.from, recipients)?;
+
+        let mut builder = match self.security {
+            SmtpSecurity::Plain => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.host)
+            }
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.host)?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.host)?,
+        }
+        .port(self.port);
+        if let Some(credentials) = &self.credentials {
+            builder = builder.credentials(credentials.clone());
+        }
+
+        builder.build().send(message).await?;
+        Ok(recipients.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    /// Minimal SMTP sink accepting one message and returning its data.
+    async fn run_sink(listener: TcpListener) -> String {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut data = String::new();
+        let mut in_data = false;
+
+        writer.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let reply: &[u8] = if in_data {
+                if line == "." {
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                    continue;
+                }
+            } else if line.starts_with("EHLO") {
+                b"250 sink\r\n"
+            } else if line == "DATA" {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if line == "QUIT" {
+                writer.write_all(b"221 bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 OK\r\n"
+            };
+            writer.write_all(reply).await.unwrap();
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn test_send_to_local_sink() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let sink = tokio::spawn(run_sink(listener));
+
+        let config = r#"{"tts":{"name":"tts","efd_name":"tucson_teststand_efd","narrative_log_url":"https://tucson-teststand.lsst.codes/narrativelog/messages","exposure_log_url":"https://tucson-teststand.lsst.codes/exposurelog/messages","zephyr_url":"https://api.zephyrscale.smartbear.com/v2/","jira_url":"https://rubinobs.atlassian.net/","rubintv_url":"https://storage.googleapis.com/rubintv_data/","email_recipients":["observers@example.org"]}}"#;
+        let site = Site::load_all_from_str(config)
+            .unwrap()
+            .remove("tts")
+            .unwrap();
+        let email =
+            NightReportEmail::from_night_data(&site, 20240813, &NightData::default()).unwrap();
+
+        let sent = SmtpMailer::new("127.0.0.1", "rolex@example.org")
+            .with_port(port)
+            .with_security(SmtpSecurity::Plain)
+            .send(&site, &email)
+            .await
+            .unwrap();
+        let data = sink.await.unwrap();
+
+        assert_eq!(sent, 1);
+        assert!(data.contains("Subject: Night report 20240813 - tts"));
+        assert!(data.contains("multipart/alternative"));
+        assert!(data.contains("Content-Type: text/plain"));
+        assert!(data.contains("Content-Type: text/html"));
+        assert!(NightReportEmail::from_night_data(
+            &Site::summit(),
+            20240813,
+            &NightData::default()
+        )
+        .unwrap()
+        .to_message("rolex@example.org", Site::summit().get_email_recipients())
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_embed_images() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = [0; 4096];
+                let _ = stream.read(&mut buffer).await.unwrap();
+                let (content_type, body): (&str, &[u8]) = if buffer.starts_with(b"GET /image.png") {
+                    ("image/png", b"\x89PNG")
+                } else {
+                    ("text/html", b"<html></html>")
+                };
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(header.as_bytes()).await.unwrap();
+                stream.write_all(body).await.unwrap();
+            }
+        });
+        let image_urls = vec![format!("{url}/image.png"), format!("{url}/login")];
+        let mut email = NightReportEmail {
+            subject: "Night report 20240813 - summit".to_owned(),
+            text: String::new(),
+            html: format!(
+                r#"<img src="{}"><img src="{}">"#,
+                image_urls[0], image_urls[1]
+            ),
+            image_urls,
+            inline_images: Vec::new(),
+        };
+
+        let embedded = email.embed_images(&RolexClient::new()).await.unwrap();
+        let message = email
+            .to_message("rolex@example.org", &["observers@example.org".to_owned()])
+            .unwrap();
+        let formatted = String::from_utf8_lossy(&message.formatted()).to_string();
+
+        assert_eq!(embedded, 1);
+        assert_eq!(
+            email.get_html(),
+            format!(r#"<img src="cid:image0"><img src="{url}/login">"#)
+        );
+        assert!(formatted.contains("multipart/related"));
+        assert!(formatted.contains("Content-ID: <image0>"));
+        assert!(formatted.contains("Content-Type: image/png"));
+    }
+}
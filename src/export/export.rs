@@ -0,0 +1,545 @@
+use chrono::SecondsFormat;
+use serde_json::{json, Map, Value};
+use std::{error::Error, io::Write};
+use thiserror::Error;
+
+use super::parquet_writer::ParquetWriter;
+use crate::{
+    block_log::block_log::BlockLog,
+    exposure_log::exposure_log::ExposureLog,
+    fault_log::fault_log::FaultLog,
+    narrative_log::narrative_log::NarrativeLog,
+    night_fetcher::night_fetcher::{NightData, Source},
+    timeline::timeline::{build_timeline, parse_time, TimelineEntry},
+};
+
+/// Separator of the items of list columns in CSV exports.
+pub const LIST_SEPARATOR: &str = ";";
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorExporting(pub(crate) String);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Result<ExportFormat, ErrorExporting> {
+        match name.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" | "json_lines" | "ndjson" => Ok(ExportFormat::JsonLines),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(ErrorExporting(format!("Unknown export format {name}"))),
+        }
+    }
+
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColumnType {
+    Text,
+    Integer,
+    Float,
+    Boolean,
+    /// Log service or InfluxDB timestamp, in UTC.
+    Timestamp,
+    TextList,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Column {
+    name: &'static str,
+    column_type: ColumnType,
+}
+
+impl Column {
+    pub const fn new(name: &'static str, column_type: ColumnType) -> Column {
+        Column { name, column_type }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn get_column_type(&self) -> ColumnType {
+        self.column_type
+    }
+}
+
+const NARRATIVE_LOG_COLUMNS: &[Column] = &[
+    Column::new("id", ColumnType::Text),
+    Column::new("site_id", ColumnType::Text),
+    Column::new("message_text", ColumnType::Text),
+    Column::new("level", ColumnType::Integer),
+    Column::new("tags", ColumnType::TextList),
+    Column::new("urls", ColumnType::TextList),
+    Column::new("time_lost", ColumnType::Float),
+    Column::new("date_begin", ColumnType::Timestamp),
+    Column::new("user_id", ColumnType::Text),
+    Column::new("user_agent", ColumnType::Text),
+    Column::new("is_human", ColumnType::Boolean),
+    Column::new("is_valid", ColumnType::Boolean),
+    Column::new("date_added", ColumnType::Timestamp),
+    Column::new("date_invalidated", ColumnType::Timestamp),
+    Column::new("parent_id", ColumnType::Text),
+    Column::new("systems", ColumnType::TextList),
+    Column::new("subsystems", ColumnType::TextList),
+    Column::new("cscs", ColumnType::TextList),
+    Column::new("date_end", ColumnType::Timestamp),
+    Column::new("components", ColumnType::TextList),
+    Column::new("primary_software_components", ColumnType::TextList),
+    Column::new("primary_hardware_components", ColumnType::TextList),
+    Column::new("category", ColumnType::Text),
+    Column::new("time_lost_type", ColumnType::Text),
+];
+
+const EXPOSURE_LOG_COLUMNS: &[Column] = &[
+    Column::new("id", ColumnType::Text),
+    Column::new("site_id", ColumnType::Text),
+    Column::new("obs_id", ColumnType::Text),
+    Column::new("instrument", ColumnType::Text),
+    Column::new("day_obs", ColumnType::Integer),
+    Column::new("seq_num", ColumnType::Integer),
+    Column::new("message_text", ColumnType::Text),
+    Column::new("level", ColumnType::Integer),
+    Column::new("tags", ColumnType::TextList),
+    Column::new("urls", ColumnType::TextList),
+    Column::new("user_id", ColumnType::Text),
+    Column::new("user_agent", ColumnType::Text),
+    Column::new("is_human", ColumnType::Boolean),
+    Column::new("is_valid", ColumnType::Boolean),
+    Column::new("exposure_flag", ColumnType::Text),
+    Column::new("date_added", ColumnType::Timestamp),
+    Column::new("date_invalidated", ColumnType::Timestamp),
+    Column::new("parent_id", ColumnType::Text),
+];
+
+const FAULT_LOG_COLUMNS: &[Column] = &[
+    Column::new("name", ColumnType::Text),
+    Column::new("severity", ColumnType::Integer),
+    Column::new("reason", ColumnType::Text),
+    Column::new("time", ColumnType::Timestamp),
+];
+
+const BLOCK_LOG_COLUMNS: &[Column] = &[
+    Column::new("time", ColumnType::Timestamp),
+    Column::new("id", ColumnType::Text),
+    Column::new("status", ColumnType::Text),
+    Column::new("hash", ColumnType::Text),
+    Column::new("sal_index", ColumnType::Integer),
+];
+
+/// Columns shared by every source: the alarm name is the id of fault log
+/// entries, and their severity the level.
+const TIMELINE_COLUMNS: &[Column] = &[
+    Column::new("source", ColumnType::Text),
+    Column::new("time", ColumnType::Timestamp),
+    Column::new("id", ColumnType::Text),
+    Column::new("user_id", ColumnType::Text),
+    Column::new("level", ColumnType::Integer),
+    Column::new("status", ColumnType::Text),
+    Column::new("message", ColumnType::Text),
+];
+
+/// What an export file holds: the entries of one source, or the merged
+/// timeline of all of them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportTable {
+    NarrativeLog,
+    ExposureLog,
+    FaultLog,
+    BlockLog,
+    Timeline,
+}
+
+impl ExportTable {
+    pub const ALL: [ExportTable; 5] = [
+        ExportTable::NarrativeLog,
+        ExportTable::ExposureLog,
+        ExportTable::FaultLog,
+        ExportTable::BlockLog,
+        ExportTable::Timeline,
+    ];
+
+    /// `None` for sources that are not logs, such as the night plan.
+    pub fn from_source(source: Source) -> Option<ExportTable> {
+        match source {
+            Source::NarrativeLog => Some(ExportTable::NarrativeLog),
+            Source::ExposureLog => Some(ExportTable::ExposureLog),
+            Source::FaultLog => Some(ExportTable::FaultLog),
+            Source::BlockLog => Some(ExportTable::BlockLog),
+            Source::NightPlan => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<ExportTable, ErrorExporting> {
+        ExportTable::ALL
+            .into_iter()
+            .find(|table| table.get_name() == name)
+            .ok_or(ErrorExporting(format!("Unknown export table {name}")))
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            ExportTable::NarrativeLog => "narrative_log",
+            ExportTable::ExposureLog => "exposure_log",
+            ExportTable::FaultLog => "fault_log",
+            ExportTable::BlockLog => "block_log",
+            ExportTable::Timeline => "timeline",
+        }
+    }
+
+    pub fn get_columns(&self) -> &'static [Column] {
+        match self {
+            ExportTable::NarrativeLog => NARRATIVE_LOG_COLUMNS,
+            ExportTable::ExposureLog => EXPOSURE_LOG_COLUMNS,
+            ExportTable::FaultLog => FAULT_LOG_COLUMNS,
+            ExportTable::BlockLog => BLOCK_LOG_COLUMNS,
+            ExportTable::Timeline => TIMELINE_COLUMNS,
+        }
+    }
+}
+
+/// Entry that can be written to an export.
+pub trait Exportable {
+    /// Full structure of the entry, for JSON Lines.
+    fn to_json(&self) -> Result<Value, serde_json::Error>;
+
+    /// Flat object keyed by column name, for CSV and Parquet.
+    fn to_row(&self) -> Result<Value, serde_json::Error> {
+        self.to_json()
+    }
+}
+
+impl Exportable for NarrativeLog {
+    fn to_json(&self) -> Result<Value, serde_json::Error> {
+        // Going through the text keeps the f32 `time_lost` at its shortest
+        // representation, 0.1 rather than 0.10000000149011612.
+        serde_json::from_str(&serde_json::to_string(self)?)
+    }
+}
+
+impl Exportable for ExposureLog {
+    fn to_json(&self) -> Result<Value, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+}
+
+impl Exportable for FaultLog {
+    fn to_json(&self) -> Result<Value, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+}
+
+impl Exportable for BlockLog {
+    fn to_json(&self) -> Result<Value, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+}
+
+impl<'a> TimelineEntry<'a> {
+    fn get_source_name(&self) -> &'static str {
+        ExportTable::from_source(self.get_source())
+            .map(|table| table.get_name())
+            .unwrap_or_default()
+    }
+}
+
+impl<'a> Exportable for TimelineEntry<'a> {
+    fn to_json(&self) -> Result<Value, serde_json::Error> {
+        let entry = match self {
+            TimelineEntry::NarrativeLog(entry) => entry.to_json()?,
+            TimelineEntry::ExposureLog(entry) => entry.to_json()?,
+            TimelineEntry::FaultLog(entry) => entry.to_json()?,
+            TimelineEntry::BlockLog(entry) => entry.to_json()?,
+        };
+        Ok(json!({
+            "source": self.get_source_name(),
+            "time": self.get_time_str(),
+            "entry": entry,
+        }))
+    }
+
+    fn to_row(&self) -> Result<Value, serde_json::Error> {
+        let (id, user_id, level, status, message) = match self {
+            TimelineEntry::NarrativeLog(entry) => (
+                entry.get_id(),
+                Some(entry.get_user_id()),
+                Some(entry.get_level()),
+                None,
+                Some(entry.get_message_text()),
+            ),
+            TimelineEntry::ExposureLog(entry) => (
+                entry.get_id(),
+                Some(entry.get_user_id()),
+                Some(entry.get_level()),
+                Some(entry.get_exposure_flag()),
+                Some(entry.get_message_text()),
+            ),
+            TimelineEntry::FaultLog(entry) => (
+                entry.get_name(),
+                None,
+                Some(entry.get_severity()),
+                None,
+                Some(entry.get_reason()),
+            ),
+            TimelineEntry::BlockLog(entry) => {
+                (entry.get_id(), None, None, Some(entry.get_status()), None)
+            }
+        };
+        Ok(json!({
+            "source": self.get_source_name(),
+            "time": self.get_time_str(),
+            "id": id,
+            "user_id": user_id,
+            "level": level,
+            "status": status,
+            "message": message,
+        }))
+    }
+}
+
+/// Text of a cell in a CSV export: list items are joined with
+/// `LIST_SEPARATOR` and timestamps normalized to RFC 3339.
+fn format_cell(column: &Column, value: Option<&Value>) -> String {
+    match (column.column_type, value) {
+        (_, None | Some(Value::Null)) => String::new(),
+        (_, Some(Value::String(text))) if column.column_type != ColumnType::Timestamp => {
+            text.to_owned()
+        }
+        (ColumnType::Timestamp, Some(Value::String(time))) => parse_time(time)
+            .map(|time| time.and_utc().to_rfc3339_opts(SecondsFormat::Micros, true))
+            .unwrap_or(time.to_owned()),
+        (_, Some(Value::Array(items))) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(text) => text.to_owned(),
+                _ => item.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(LIST_SEPARATOR),
+        (_, Some(value)) => value.to_string(),
+    }
+}
+
+enum Output<W: Write + Send> {
+    Csv(Box<csv::Writer<W>>),
+    JsonLines(W),
+    Parquet(Box<ParquetWriter<W>>),
+}
+
+/// Streaming writer of one export table. Entries are written as they come,
+/// so a range of nights can be exported one night at a time.
+pub struct ExportWriter<W: Write + Send> {
+    table: ExportTable,
+    output: Output<W>,
+}
+
+impl<W: Write + Send> ExportWriter<W> {
+    pub fn new(
+        format: ExportFormat,
+        table: ExportTable,
+        writer: W,
+    ) -> Result<ExportWriter<W>, Box<dyn Error>> {
+        let output = match format {
+            ExportFormat::Csv => {
+                let mut csv_writer = csv::Writer::from_writer(writer);
+                csv_writer.write_record(table.get_columns().iter().map(Column::get_name))?;
+                Output::Csv(Box::new(csv_writer))
+            }
+            ExportFormat::JsonLines => Output::JsonLines(writer),
+            ExportFormat::Parquet => {
+                Output::Parquet(Box::new(ParquetWriter::new(writer, table.get_columns())?))
+            }
+        };
+        Ok(ExportWriter { table, output })
+    }
+
+    pub fn get_table(&self) -> ExportTable {
+        self.table
+    }
+
+    pub fn write<T: Exportable>(&mut self, entry: &T) -> Result<(), Box<dyn Error>> {
+        match &mut self.output {
+            Output::Csv(csv_writer) => {
+                let row = entry.to_row()?;
+                let row = row.as_object().cloned().unwrap_or_else(Map::new);
+                csv_writer.write_record(
+                    self.table
+                        .get_columns()
+                        .iter()
+                        .map(|column| format_cell(column, row.get(column.name))),
+                )?;
+            }
+            Output::JsonLines(writer) => {
+                serde_json::to_writer(&mut *writer, &entry.to_json()?)?;
+                writer.write_all(b"\n")?;
+            }
+            Output::Parquet(parquet_writer) => parquet_writer.write(entry.to_row()?)?,
+        }
+        Ok(())
+    }
+
+    /// Write the entries of the table found in `night_data`.
+    pub fn write_night(&mut self, night_data: &NightData) -> Result<(), Box<dyn Error>> {
+        match self.table {
+            ExportTable::NarrativeLog => night_data
+                .get_narrative_logs()
+                .iter()
+                .try_for_each(|entry| self.write(entry)),
+            ExportTable::ExposureLog => night_data
+                .get_exposure_logs()
+                .iter()
+                .try_for_each(|entry| self.write(entry)),
+            ExportTable::FaultLog => night_data
+                .get_fault_logs()
+                .iter()
+                .try_for_each(|entry| self.write(entry)),
+            ExportTable::BlockLog => night_data
+                .get_block_logs()
+                .iter()
+                .try_for_each(|entry| self.write(entry)),
+            ExportTable::Timeline => build_timeline(night_data)
+                .iter()
+                .try_for_each(|entry| self.write(entry)),
+        }
+    }
+
+    /// Flush the export, writing the Parquet footer, and give back the
+    /// underlying writer.
+    pub fn finish(self) -> Result<W, Box<dyn Error>> {
+        match self.output {
+            Output::Csv(csv_writer) => (*csv_writer)
+                .into_inner()
+                .map_err(|error| Box::new(ErrorExporting(error.to_string())).into()),
+            Output::JsonLines(mut writer) => {
+                writer.flush()?;
+                Ok(writer)
+            }
+            Output::Parquet(parquet_writer) => parquet_writer.finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_csv_and_json_lines() {
+        let narrative_log_json = r#"{"id":"8b9d2ff1-5a2e-4b56-9f1c-1c1e4c7f2d1a","site_id":"summit","message_text":"Dome, \"stuck\"","level":0,"tags":["dome","fault"],"urls":[],"time_lost":0.5,"date_begin":"2024-08-14T01:00:00","user_id":"observer","user_agent":"LOVE","is_human":true,"is_valid":true,"date_added":"2024-08-14T01:02:03.5","date_invalidated":null,"parent_id":null,"systems":null,"subsystems":null,"cscs":null,"date_end":"2024-08-14T01:30:00","components":["ATDome"],"primary_software_components":[],"primary_hardware_components":[],"category":"","time_lost_type":null}"#;
+        let narrative_log: NarrativeLog = serde_json::from_str(narrative_log_json).unwrap();
+
+        let mut csv_writer =
+            ExportWriter::new(ExportFormat::Csv, ExportTable::NarrativeLog, Vec::new()).unwrap();
+        csv_writer.write(&narrative_log).unwrap();
+        let csv = String::from_utf8(csv_writer.finish().unwrap()).unwrap();
+        let mut json_writer = ExportWriter::new(
+            ExportFormat::JsonLines,
+            ExportTable::NarrativeLog,
+            Vec::new(),
+        )
+        .unwrap();
+        json_writer.write(&narrative_log).unwrap();
+        json_writer.write(&narrative_log).unwrap();
+        let json_lines = String::from_utf8(json_writer.finish().unwrap()).unwrap();
+
+        let mut lines = csv.lines();
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("id,site_id,message_text,level,tags,urls,time_lost,date_begin"));
+        let row = lines.next().unwrap();
+        assert!(
+            row.contains(r#","Dome, ""stuck""",0,dome;fault,,0.5,2024-08-14T01:00:00.000000Z,"#)
+        );
+        assert!(row.contains(",2024-08-14T01:02:03.500000Z,,,,,,"));
+        assert_eq!(narrative_log.to_json().unwrap()["time_lost"], 0.5);
+        assert_eq!(json_lines.lines().count(), 2);
+        assert!(json_lines.contains(r#""id":"8b9d2ff1-5a2e-4b56-9f1c-1c1e4c7f2d1a""#));
+        assert!(json_lines.contains(r#""tags":["dome","fault"]"#));
+    }
+
+    #[test]
+    fn test_timeline_row() {
+        let fault_log: FaultLog = serde_json::from_str(
+            r#"{"name":"Enabled.ATDome","severity":3,"reason":"Fault","time":"2024-08-14T01:00:00Z"}"#,
+        )
+        .unwrap();
+
+        let row = TimelineEntry::FaultLog(&fault_log).to_row().unwrap();
+        let json = TimelineEntry::FaultLog(&fault_log).to_json().unwrap();
+
+        assert_eq!(row["source"], "fault_log");
+        assert_eq!(row["id"], "Enabled.ATDome");
+        assert_eq!(row["level"], 3);
+        assert_eq!(row["status"], Value::Null);
+        assert_eq!(json["entry"]["reason"], "Fault");
+        assert_eq!(
+            ExportTable::from_name("timeline").unwrap(),
+            ExportTable::Timeline
+        );
+    }
+
+    #[test]
+    fn test_row_keys_match_columns() {
+        let narrative_log: NarrativeLog = serde_json::from_str(r#"{"id":"8b9d2ff1-5a2e-4b56-9f1c-1c1e4c7f2d1a","site_id":"summit","message_text":"Dome stuck","level":0,"tags":[],"urls":[],"time_lost":0.1,"date_begin":"2024-08-14T01:00:00","user_id":"observer","user_agent":"LOVE","is_human":true,"is_valid":true,"date_added":"2024-08-14T01:02:03.5","date_invalidated":null,"parent_id":null,"systems":null,"subsystems":null,"cscs":null,"date_end":"2024-08-14T01:30:00","components":null,"primary_software_components":[],"primary_hardware_components":[],"category":"","time_lost_type":null}"#).unwrap();
+        let exposure_log: ExposureLog = serde_json::from_str(r#"{"id":"000f68b2-e560-40ce-bdbc-a57b3363e1e9","site_id":"summit","obs_id":"AT_O_20220608_000168","instrument":"LATISS","day_obs":20220608,"seq_num":168,"message_text":"","level":20,"tags":[],"urls":[],"user_id":"slimleashma","user_agent":"notebook:nublado","is_human":true,"is_valid":true,"exposure_flag":"junk","date_added":"2022-06-08T23:19:38.906593","date_invalidated":null,"parent_id":null}"#).unwrap();
+        let fault_log: FaultLog = serde_json::from_str(
+            r#"{"name":"Enabled.ATDome","severity":3,"reason":"Fault","time":"2024-08-14T01:00:00Z"}"#,
+        )
+        .unwrap();
+        let block_log: BlockLog = serde_json::from_str(
+            r#"{"time":"2024-08-14T01:00:00Z","id":"BLOCK-T17","status":"COMPLETED","hash":"abc","sal_index":2}"#,
+        )
+        .unwrap();
+        let keys = |row: Value| -> Vec<String> {
+            let mut keys: Vec<String> = row.as_object().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        let names = |table: ExportTable| -> Vec<String> {
+            let mut names: Vec<String> = table
+                .get_columns()
+                .iter()
+                .map(|column| column.get_name().to_owned())
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(
+            keys(narrative_log.to_row().unwrap()),
+            names(ExportTable::NarrativeLog)
+        );
+        assert_eq!(
+            keys(exposure_log.to_row().unwrap()),
+            names(ExportTable::ExposureLog)
+        );
+        assert_eq!(
+            keys(fault_log.to_row().unwrap()),
+            names(ExportTable::FaultLog)
+        );
+        assert_eq!(
+            keys(block_log.to_row().unwrap()),
+            names(ExportTable::BlockLog)
+        );
+        assert_eq!(
+            keys(TimelineEntry::BlockLog(&block_log).to_row().unwrap()),
+            names(ExportTable::Timeline)
+        );
+        assert_eq!(narrative_log.to_row().unwrap()["time_lost"], 0.1);
+    }
+}
@@ -0,0 +1,219 @@
+use arrow_array::{
+    builder::{
+        BooleanBuilder, Float64Builder, Int64Builder, ListBuilder, StringBuilder,
+        TimestampMicrosecondBuilder,
+    },
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use serde_json::Value;
+use std::{error::Error, io::Write, sync::Arc};
+
+use super::export::{Column, ColumnType, ErrorExporting};
+use crate::timeline::timeline::parse_time;
+
+/// Rows buffered before being converted to an Arrow record batch.
+const BATCH_SIZE: usize = 8192;
+
+fn data_type(column_type: ColumnType) -> DataType {
+    match column_type {
+        ColumnType::Text => DataType::Utf8,
+        ColumnType::Integer => DataType::Int64,
+        ColumnType::Float => DataType::Float64,
+        ColumnType::Boolean => DataType::Boolean,
+        ColumnType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        ColumnType::TextList => DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+    }
+}
+
+/// Arrow schema of the columns, every column nullable.
+pub fn build_schema(columns: &[Column]) -> Schema {
+    Schema::new(
+        columns
+            .iter()
+            .map(|column| Field::new(column.get_name(), data_type(column.get_column_type()), true))
+            .collect::<Vec<Field>>(),
+    )
+}
+
+/// Column of `rows` as an Arrow array. Timestamps that cannot be parsed are
+/// an error rather than a silent null.
+fn build_array(column: &Column, rows: &[Value]) -> Result<ArrayRef, ErrorExporting> {
+    let values = rows.iter().map(|row| &row[column.get_name()]);
+    Ok(match column.get_column_type() {
+        ColumnType::Text => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                match value {
+                    Value::Null => builder.append_null(),
+                    Value::String(text) => builder.append_value(text),
+                    _ => builder.append_value(value.to_string()),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Integer => {
+            let mut builder = Int64Builder::new();
+            for value in values {
+                builder.append_option(value.as_i64());
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Float => {
+            let mut builder = Float64Builder::new();
+            for value in values {
+                builder.append_option(value.as_f64());
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Boolean => {
+            let mut builder = BooleanBuilder::new();
+            for value in values {
+                builder.append_option(value.as_bool());
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Timestamp => {
+            let mut builder = TimestampMicrosecondBuilder::new().with_timezone("UTC");
+            for value in values {
+                if value.is_null() {
+                    builder.append_null();
+                    continue;
+                }
+                let time = value.as_str().and_then(parse_time).ok_or_else(|| {
+                    ErrorExporting(format!(
+                        "Invalid timestamp {value} in column {}",
+                        column.get_name()
+                    ))
+                })?;
+                builder.append_value(time.and_utc().timestamp_micros());
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::TextList => {
+            let mut builder = ListBuilder::new(StringBuilder::new());
+            for value in values {
+                match value.as_array() {
+                    Some(items) => {
+                        for item in items {
+                            builder.values().append_option(item.as_str());
+                        }
+                        builder.append(true);
+                    }
+                    None => builder.append(false),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+    })
+}
+
+/// Parquet file writer with a typed schema, fed rows keyed by column name
+/// and flushing them in record batches.
+pub struct ParquetWriter<W: Write + Send> {
+    columns: &'static [Column],
+    schema: Arc<Schema>,
+    rows: Vec<Value>,
+    writer: ArrowWriter<W>,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    pub fn new(writer: W, columns: &'static [Column]) -> Result<ParquetWriter<W>, Box<dyn Error>> {
+        let schema = Arc::new(build_schema(columns));
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        Ok(ParquetWriter {
+            columns,
+            schema: schema.clone(),
+            rows: Vec::new(),
+            writer: ArrowWriter::try_new(writer, schema, Some(properties))?,
+        })
+    }
+
+    pub fn write(&mut self, row: Value) -> Result<(), Box<dyn Error>> {
+        self.rows.push(row);
+        if self.rows.len() >= BATCH_SIZE {
+            self.flush_rows()?;
+        }
+        Ok(())
+    }
+
+    fn flush_rows(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let arrays: Vec<ArrayRef> = self
+            .columns
+            .iter()
+            .map(|column| build_array(column, &self.rows))
+            .collect::<Result<_, _>>()?;
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)?;
+        self.writer.write(&batch)?;
+        self.rows.clear();
+        Ok(())
+    }
+
+    /// Write the remaining rows and the file footer.
+    pub fn finish(mut self) -> Result<W, Box<dyn Error>> {
+        self.flush_rows()?;
+        Ok(self.writer.into_inner()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::export::export::{ExportFormat, ExportTable, ExportWriter};
+    use crate::narrative_log::narrative_log::NarrativeLog;
+    use arrow_array::{cast::AsArray, types::TimestampMicrosecondType, Array};
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn test_typed_schema() {
+        let narrative_log_json = r#"{"id":"8b9d2ff1-5a2e-4b56-9f1c-1c1e4c7f2d1a","site_id":"summit","message_text":"Dome stuck","level":0,"tags":["dome","fault"],"urls":[],"time_lost":0.5,"date_begin":"2024-08-14T01:00:00","user_id":"observer","user_agent":"LOVE","is_human":true,"is_valid":true,"date_added":"2024-08-14T01:02:03.5","date_invalidated":null,"parent_id":null,"systems":null,"subsystems":null,"cscs":null,"date_end":"2024-08-14T01:30:00","components":["ATDome"],"primary_software_components":[],"primary_hardware_components":[],"category":"","time_lost_type":null}"#;
+        let narrative_log: NarrativeLog = serde_json::from_str(narrative_log_json).unwrap();
+
+        let mut writer =
+            ExportWriter::new(ExportFormat::Parquet, ExportTable::NarrativeLog, Vec::new())
+                .unwrap();
+        writer.write(&narrative_log).unwrap();
+        let data = writer.finish().unwrap();
+        let batches: Vec<RecordBatch> = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(data))
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let mut invalid =
+            ExportWriter::new(ExportFormat::Parquet, ExportTable::NarrativeLog, Vec::new())
+                .unwrap();
+        invalid
+            .write(
+                &serde_json::from_str::<NarrativeLog>(
+                    &narrative_log_json.replace("2024-08-14T01:02:03.5", "yesterday"),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(
+            batch.schema().as_ref(),
+            &build_schema(ExportTable::NarrativeLog.get_columns())
+        );
+        let date_added = batch
+            .column_by_name("date_added")
+            .unwrap()
+            .as_primitive::<TimestampMicrosecondType>();
+        assert_eq!(date_added.value(0), 1723597323500000);
+        let tags = batch.column_by_name("tags").unwrap().as_list::<i32>();
+        assert_eq!(tags.value(0).as_string::<i32>().value(1), "fault");
+        assert!(batch.column_by_name("systems").unwrap().is_null(0));
+        assert!(invalid.finish().is_err());
+    }
+}
@@ -2,6 +2,14 @@ use askama::Template;
 use std::{collections::HashMap, error::Error};
 use url::Url;
 
+use crate::{
+    client::client::RolexClient,
+    jira::jira::{extract_log_issue_keys, select_jira_issues, JiraIssue},
+    markdown::markdown,
+    site::site::Site,
+    timeline::timeline::Filterable,
+};
+
 #[derive(Debug, Deserialize, Serialize, Default, Template)]
 #[template(path = "exposure_log.html")]
 pub struct ExposureLog {
@@ -23,32 +31,94 @@ pub struct ExposureLog {
     date_added: Option<String>,
     date_invalidated: Option<String>,
     parent_id: Option<String>,
+    #[serde(skip)]
+    jira_issues: Vec<JiraIssue>,
 }
 
 impl ExposureLog {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+    pub fn get_site_id(&self) -> &str {
+        &self.site_id
+    }
     pub fn get_date_added(&self) -> &Option<String> {
         &self.date_added
     }
+    pub fn get_message_text(&self) -> &str {
+        &self.message_text
+    }
+    /// Built-in profile of the site the entry was logged at.
+    pub fn get_site(&self) -> Option<Site> {
+        Site::from_name(&self.site_id).ok()
+    }
+    /// Message rendered from Markdown to sanitized HTML.
+    pub fn get_message_html(&self, site: &Site) -> String {
+        markdown::render_html(&self.message_text, site)
+    }
+    /// Message with the Markdown markup removed.
+    pub fn get_message_plain_text(&self) -> String {
+        markdown::render_plain_text(&self.message_text)
+    }
+    /// Jira issue keys of `projects` referenced in the message or urls.
+    pub fn get_issue_keys(&self, projects: &[String]) -> Vec<String> {
+        extract_log_issue_keys(&self.message_text, &self.urls, projects)
+    }
+
+    /// Keep the issues referenced by this entry out of `jira_issues`.
+    pub fn attach_jira_issues(
+        &mut self,
+        jira_issues: &HashMap<String, JiraIssue>,
+        projects: &[String],
+    ) {
+        self.jira_issues = select_jira_issues(&self.get_issue_keys(projects), jira_issues);
+    }
+
+    pub fn get_jira_issues(&self) -> &[JiraIssue] {
+        &self.jira_issues
+    }
     pub fn get_labels_as_str(&self) -> String {
         self.instrument.to_owned()
     }
+    pub fn get_obs_id(&self) -> &str {
+        &self.obs_id
+    }
+    pub fn get_instrument(&self) -> &str {
+        &self.instrument
+    }
+    pub fn get_level(&self) -> usize {
+        self.level
+    }
+    pub fn get_user_id(&self) -> &str {
+        &self.user_id
+    }
+    pub fn get_exposure_flag(&self) -> &str {
+        &self.exposure_flag
+    }
+    /// Monitor image of the exposure on the RubinTV of the entry's site.
     pub fn get_attached_images(&self) -> Vec<String> {
+        self.get_site()
+            .map(|site| self.get_attached_images_from(site.get_rubintv_url()))
+            .unwrap_or_default()
+    }
+    pub fn get_attached_images_from(&self, rubintv_url: &str) -> Vec<String> {
         let day_obs = self.day_obs as f64;
         let yyyy = ((self.day_obs / 10000) as f64).round();
         let mm = ((day_obs - yyyy * 10000.) / 100.).round();
         let dd = (day_obs - yyyy * 10000. - mm * 100.).round();
         let seq_num = self.seq_num;
         vec![format!(
-            "https://storage.googleapis.com/rubintv_data/auxtel_monitor/auxtel-monitor_dayObs_{yyyy:04.0}-{mm:02.0}-{dd:02.0}_seqNum_{seq_num}.png"
+            "{rubintv_url}auxtel_monitor/auxtel-monitor_dayObs_{yyyy:04.0}-{mm:02.0}-{dd:02.0}_seqNum_{seq_num}.png"
         )]
         // self.urls.iter().filter_map(|url| if url.ends_with(""))
     }
     pub async fn retrieve(
-        url: &str,
+        client: &RolexClient,
+        site: &Site,
         params: &Option<HashMap<String, String>>,
     ) -> Result<Vec<ExposureLog>, Box<dyn Error>> {
         let url = {
-            let mut url = Url::parse(url)?;
+            let mut url = Url::parse(site.get_exposure_log_url())?;
 
             if let Some(params) = params {
                 for (key, value) in params {
@@ -58,7 +128,7 @@ impl ExposureLog {
             url
         };
 
-        let response = reqwest::get(&url.to_string()).await?;
+        let response = client.send(client.get_http().get(url)).await?;
 
         let response_text = response.text().await?;
 
@@ -68,6 +138,18 @@ impl ExposureLog {
     }
 }
 
+impl Filterable for ExposureLog {
+    fn get_filter_attributes(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("source", "ExposureLog".to_owned()),
+            ("instrument", self.instrument.to_owned()),
+            ("severity", self.level.to_string()),
+            ("user", self.user_id.to_owned()),
+            ("flag", self.exposure_flag.to_owned()),
+        ]
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
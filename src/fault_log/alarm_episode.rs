@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use super::fault_log::FaultLog;
+
+/// Watcher severity of an alarm that is not raised.
+const SEVERITY_NONE: usize = 1;
+
+/// Period during which one Watcher alarm stayed raised, from its first
+/// event above `SEVERITY_NONE` until it went back to it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct AlarmEpisode {
+    name: String,
+    max_severity: usize,
+    reason: String,
+    start: String,
+    end: Option<String>,
+    count: usize,
+}
+
+impl AlarmEpisode {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_max_severity(&self) -> usize {
+        self.max_severity
+    }
+
+    /// Reason given when the alarm reached its maximum severity.
+    pub fn get_reason(&self) -> &str {
+        &self.reason
+    }
+
+    pub fn get_start(&self) -> &str {
+        &self.start
+    }
+
+    /// `None` when the alarm was still raised at the end of the window.
+    pub fn get_end(&self) -> &Option<String> {
+        &self.end
+    }
+
+    /// Number of alarm events in the episode.
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+
+    /// Group alarm events into episodes, ordered by start time.
+    pub fn from_fault_logs(fault_logs: &[FaultLog]) -> Vec<AlarmEpisode> {
+        let mut fault_logs: Vec<&FaultLog> = fault_logs.iter().collect();
+        fault_logs.sort_by(|a, b| a.get_time().cmp(b.get_time()));
+
+        let mut open: HashMap<&str, AlarmEpisode> = HashMap::new();
+        let mut episodes = Vec::new();
+
+        for fault_log in fault_logs {
+            let name = fault_log.get_name();
+            let severity = fault_log.get_severity();
+
+            if severity <= SEVERITY_NONE {
+                if let Some(mut episode) = open.remove(name) {
+                    episode.end = Some(fault_log.get_time().to_owned());
+                    episode.count += 1;
+                    episodes.push(episode);
+                }
+                continue;
+            }
+
+            let episode = open.entry(name).or_insert_with(|| AlarmEpisode {
+                name: name.to_owned(),
+                start: fault_log.get_time().to_owned(),
+                ..Default::default()
+            });
+            if severity > episode.max_severity {
+                episode.max_severity = severity;
+                episode.reason = fault_log.get_reason().to_owned();
+            }
+            episode.count += 1;
+        }
+
+        episodes.extend(open.into_values());
+        episodes.sort_by(|a, b| a.start.cmp(&b.start).then(a.name.cmp(&b.name)));
+        episodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_from_fault_logs() {
+        let fault_logs: Vec<FaultLog> = serde_json::from_str(
+            r#"[
+                {"name":"Enabled.ATDome","severity":3,"reason":"Fault","time":"2024-08-14T02:00:00Z"},
+                {"name":"Heartbeat.ATMCS","severity":2,"reason":"Late","time":"2024-08-14T02:30:00Z"},
+                {"name":"Enabled.ATDome","severity":4,"reason":"Offline","time":"2024-08-14T02:10:00Z"},
+                {"name":"Enabled.ATDome","severity":1,"reason":"","time":"2024-08-14T02:20:00Z"}
+            ]"#,
+        )
+        .unwrap();
+
+        let episodes = AlarmEpisode::from_fault_logs(&fault_logs);
+
+        assert_eq!(episodes.len(), 2);
+        assert_eq!(episodes[0].get_name(), "Enabled.ATDome");
+        assert_eq!(episodes[0].get_max_severity(), 4);
+        assert_eq!(episodes[0].get_reason(), "Offline");
+        assert_eq!(
+            episodes[0].get_end(),
+            &Some("2024-08-14T02:20:00Z".to_owned())
+        );
+        assert_eq!(episodes[0].get_count(), 3);
+        assert_eq!(episodes[1].get_end(), &None);
+    }
+}
@@ -1,13 +1,11 @@
 use askama::Template;
 use chrono::NaiveDateTime;
-use lsst_efd_client::EfdAuth;
-use reqwest::Client;
 use std::error::Error as StdError;
-use thiserror::Error;
 
-#[derive(Clone, Debug, Eq, Error, PartialEq)]
-#[error("{0}")]
-struct ErrorRetrievingFaultLog(String);
+use crate::{
+    client::client::RolexClient, efd::efd_query::EfdQuery, site::site::Site,
+    timeline::timeline::Filterable,
+};
 
 #[derive(Debug, Deserialize, Serialize, Default, Template)]
 #[template(path = "fault_log.html", ext = "html")]
@@ -18,76 +16,69 @@ pub struct FaultLog {
     time: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
-struct QueryResult {
-    results: Vec<Payload>,
-}
+type Row = (String, String, String, usize);
 
-#[derive(Debug, Deserialize, Serialize, Default)]
-struct Payload {
-    statement_id: usize,
-    series: Vec<Series>,
-}
+const SELECT: &str = r#"SELECT "time","name","reason","severity" FROM "efd"."autogen"."lsst.sal.Watcher.logevent_alarm""#;
 
-#[derive(Debug, Deserialize, Serialize, Default)]
-struct Series {
-    name: String,
-    columns: Vec<String>,
-    values: Vec<(String, String, String, usize)>,
-}
+impl FaultLog {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
 
-impl Series {
-    fn into_fault_log(&self) -> Vec<FaultLog> {
-        self.values
-            .iter()
-            .map(|(time, name, reason, severity)| FaultLog {
-                name: name.to_string(),
-                severity: *severity,
-                reason: reason.to_string(),
-                time: time.to_string(),
-            })
-            .collect()
+    pub fn get_severity(&self) -> usize {
+        self.severity
+    }
+
+    pub fn get_reason(&self) -> &str {
+        &self.reason
+    }
+
+    pub fn get_time(&self) -> &str {
+        &self.time
     }
-}
 
-impl FaultLog {
     pub async fn retrieve(
+        client: &RolexClient,
+        site: &Site,
+        date_start: &NaiveDateTime,
+        date_end: &NaiveDateTime,
+    ) -> Result<Vec<FaultLog>, Box<dyn StdError>> {
+        FaultLog::retrieve_with(client, site, &EfdQuery::default(), date_start, date_end).await
+    }
+
+    /// Retrieve alarms over a possibly long window, chunked according to
+    /// `efd_query`.
+    pub async fn retrieve_with(
+        client: &RolexClient,
+        site: &Site,
+        efd_query: &EfdQuery,
         date_start: &NaiveDateTime,
         date_end: &NaiveDateTime,
     ) -> Result<Vec<FaultLog>, Box<dyn StdError>> {
-        let efd_auth = EfdAuth::new("base_efd").await?;
+        let rows: Vec<Row> = efd_query
+            .query(client, site, SELECT, date_start, date_end, |row: &Row| {
+                &row.0
+            })
+            .await?;
 
-        let influxdb_url = format!(
-            "https://{}:{}/influxdb/query",
-            efd_auth.get_host(),
-            efd_auth.get_port(),
-        );
-        // Create a reqwest client
-        let client = Client::new();
+        Ok(rows
+            .into_iter()
+            .map(|(time, name, reason, severity)| FaultLog {
+                name,
+                severity,
+                reason,
+                time,
+            })
+            .collect())
+    }
+}
 
-        let query = format!(
-            r#"SELECT "time","name","reason","severity" FROM "efd"."autogen"."lsst.sal.Watcher.logevent_alarm" WHERE time > '{date_start}' AND time < '{date_end}'"#
-        );
-        println!("{query}");
-        // Construct the full URL with query parameters
-        let response = client
-            .get(influxdb_url)
-            .basic_auth(efd_auth.get_username(), Some(efd_auth.get_password()))
-            .query(&[("db", "efd"), ("q", &query)])
-            .send()
-            .await?; // Check the status code
-        if response.status().is_success() {
-            // Parse the response JSON
-            let text = response.text().await?;
-            let query_result: QueryResult = serde_json::from_str(&text)?;
-            Ok(query_result.results[0].series[0].into_fault_log())
-        } else {
-            println!("{response:?}");
-            Err(Box::new(ErrorRetrievingFaultLog(format!(
-                "Error: {:?}",
-                response
-            ))))
-        }
-        // println!("{ping:?}");
+impl Filterable for FaultLog {
+    fn get_filter_attributes(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("source", "FaultLog".to_owned()),
+            ("component", self.name.to_owned()),
+            ("severity", self.severity.to_string()),
+        ]
     }
 }
@@ -1 +1,2 @@
+pub mod alarm_episode;
 pub mod fault_log;
@@ -0,0 +1,333 @@
+use askama::Template;
+use chrono::{SecondsFormat, Utc};
+use url::Url;
+
+use crate::{
+    exposure_log::exposure_log::ExposureLog, narrative_log::narrative_log::NarrativeLog,
+    site::site::Site, timeline::timeline::parse_time,
+};
+
+/// Longest entry title, in characters, taken from the message.
+const MAX_TITLE_LENGTH: usize = 80;
+
+/// Restricts the entries of a feed to one site and/or one component.
+#[derive(Clone, Debug, Default)]
+pub struct FeedFilter {
+    site_id: Option<String>,
+    component: Option<String>,
+}
+
+impl FeedFilter {
+    pub fn with_site_id(mut self, site_id: &str) -> FeedFilter {
+        self.site_id = Some(site_id.to_owned());
+        self
+    }
+
+    /// Keep entries listing `component` among their systems, subsystems or
+    /// components (the instrument for exposure log entries).
+    pub fn with_component(mut self, component: &str) -> FeedFilter {
+        self.component = Some(component.to_owned());
+        self
+    }
+
+    fn matches(&self, site_id: &str, categories: &[String]) -> bool {
+        self.site_id.as_ref().is_none_or(|wanted| wanted == site_id)
+            && self
+                .component
+                .as_ref()
+                .is_none_or(|wanted| categories.contains(wanted))
+    }
+}
+
+/// Image attached to an entry.
+#[derive(Clone, Debug, Default)]
+pub struct Enclosure {
+    url: String,
+    media_type: String,
+}
+
+impl Enclosure {
+    fn new(url: &str) -> Enclosure {
+        let media_type = if url.ends_with(".png") {
+            "image/png"
+        } else if url.ends_with(".jpg") || url.ends_with(".jpeg") {
+            "image/jpeg"
+        } else {
+            "application/octet-stream"
+        };
+        Enclosure {
+            url: url.to_owned(),
+            media_type: media_type.to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FeedEntry {
+    id: String,
+    title: String,
+    updated: String,
+    author: String,
+    categories: Vec<String>,
+    enclosures: Vec<Enclosure>,
+    content: String,
+}
+
+impl FeedEntry {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_updated(&self) -> &str {
+        &self.updated
+    }
+
+    pub fn get_categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    /// Entry for `narrative_log`, unless it has no usable timestamp.
+    fn from_narrative_log(site: &Site, narrative_log: &NarrativeLog) -> Option<FeedEntry> {
+        let categories: Vec<String> = [
+            narrative_log.get_systems(),
+            narrative_log.get_subsystems(),
+            narrative_log.get_components(),
+        ]
+        .into_iter()
+        .flatten()
+        .flatten()
+        .cloned()
+        .collect();
+
+        Some(FeedEntry {
+            id: format!("urn:uuid:{}", narrative_log.get_id()),
+            title: get_title(&narrative_log.get_message_plain_text()),
+            updated: format_updated(narrative_log.get_date_added())?,
+            author: narrative_log.get_user_id().to_owned(),
+            categories,
+            enclosures: narrative_log
+                .get_attached_images()
+                .iter()
+                .map(|url| Enclosure::new(url))
+                .collect(),
+            content: narrative_log.get_message_html(site),
+        })
+    }
+
+    /// Entry for `exposure_log`, unless it has no usable timestamp.
+    fn from_exposure_log(site: &Site, exposure_log: &ExposureLog) -> Option<FeedEntry> {
+        Some(FeedEntry {
+            id: format!("urn:uuid:{}", exposure_log.get_id()),
+            title: get_title(&format!(
+                "{}: {}",
+                exposure_log.get_obs_id(),
+                exposure_log.get_message_plain_text()
+            )),
+            updated: format_updated(exposure_log.get_date_added().as_deref()?)?,
+            author: exposure_log.get_user_id().to_owned(),
+            categories: vec![exposure_log.get_instrument().to_owned()],
+            enclosures: exposure_log
+                .get_attached_images_from(site.get_rubintv_url())
+                .iter()
+                .map(|url| Enclosure::new(url))
+                .collect(),
+            content: exposure_log.get_message_html(site),
+        })
+    }
+}
+
+/// First line of the message, shortened for use as an entry title.
+fn get_title(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    if line.chars().count() <= MAX_TITLE_LENGTH {
+        return line.to_owned();
+    }
+    let title: String = line.chars().take(MAX_TITLE_LENGTH - 1).collect();
+    format!("{title}…")
+}
+
+/// Log service timestamps (naive, UTC) as RFC 3339, as Atom requires.
+fn format_updated(time: &str) -> Option<String> {
+    parse_time(time).map(|time| time.and_utc().to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Atom feed of narrative log, and optionally exposure log, entries, newest
+/// first. Entry ids are derived from the log entry ids so readers do not
+/// show the same entry twice across refreshes. Entries without a usable
+/// timestamp are left out.
+#[derive(Debug, Default, Template)]
+#[template(path = "atom_feed.xml")]
+pub struct AtomFeed {
+    id: String,
+    title: String,
+    updated: String,
+    entries: Vec<FeedEntry>,
+}
+
+impl AtomFeed {
+    pub fn from_logs(
+        site: &Site,
+        filter: &FeedFilter,
+        narrative_logs: &[NarrativeLog],
+        exposure_logs: Option<&[ExposureLog]>,
+    ) -> AtomFeed {
+        let mut entries: Vec<FeedEntry> = narrative_logs
+            .iter()
+            .filter_map(|narrative_log| {
+                let entry = FeedEntry::from_narrative_log(site, narrative_log)?;
+                filter
+                    .matches(narrative_log.get_site_id(), &entry.categories)
+                    .then_some(entry)
+            })
+            .chain(
+                exposure_logs
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|exposure_log| {
+                        let entry = FeedEntry::from_exposure_log(site, exposure_log)?;
+                        filter
+                            .matches(exposure_log.get_site_id(), &entry.categories)
+                            .then_some(entry)
+                    }),
+            )
+            .collect();
+        entries.sort_by(|a, b| b.updated.cmp(&a.updated));
+
+        let updated = entries
+            .first()
+            .map(|entry| entry.updated.to_owned())
+            .unwrap_or_else(|| Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true));
+
+        AtomFeed {
+            id: AtomFeed::get_feed_id(site, filter, exposure_logs.is_some()),
+            title: AtomFeed::get_feed_title(site, filter, exposure_logs.is_some()),
+            updated,
+            entries,
+        }
+    }
+
+    /// Feed id unique to the site, filter and included sources, so that
+    /// readers subscribed to several feeds keep them apart.
+    fn get_feed_id(site: &Site, filter: &FeedFilter, with_exposure_logs: bool) -> String {
+        let Ok(mut url) = Url::parse(site.get_narrative_log_url()) else {
+            return site.get_narrative_log_url().to_owned();
+        };
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(site_id) = &filter.site_id {
+                query.append_pair("site_id", site_id);
+            }
+            if let Some(component) = &filter.component {
+                query.append_pair("component", component);
+            }
+            if with_exposure_logs {
+                query.append_pair("exposure_log", "true");
+            }
+        }
+        if url.query() == Some("") {
+            url.set_query(None);
+        }
+        url.to_string()
+    }
+
+    fn get_feed_title(site: &Site, filter: &FeedFilter, with_exposure_logs: bool) -> String {
+        let mut title = if with_exposure_logs {
+            format!("Narrative and exposure log - {}", site.get_name())
+        } else {
+            format!("Narrative log - {}", site.get_name())
+        };
+        for part in [&filter.site_id, &filter.component].into_iter().flatten() {
+            title.push_str(&format!(" - {part}"));
+        }
+        title
+    }
+
+    pub fn get_entries(&self) -> &[FeedEntry] {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_from_exposure_logs() {
+        let exposure_log_json = r#"{"id":"000f68b2-e560-40ce-bdbc-a57b3363e1e9","site_id":"summit","obs_id":"AT_O_20220608_000168","instrument":"LATISS","day_obs":20220608,"seq_num":168,"message_text":"Satellite <trail>","level":20,"tags":[],"urls":[],"user_id":"slimleashma","user_agent":"notebook:nublado","is_human":true,"is_valid":true,"exposure_flag":"junk","date_added":"2022-06-08T23:19:38.906593","date_invalidated":null,"parent_id":null}"#;
+        let exposure_logs: Vec<ExposureLog> =
+            vec![serde_json::from_str(exposure_log_json).unwrap()];
+        let site = Site::summit();
+
+        let feed = AtomFeed::from_logs(&site, &FeedFilter::default(), &[], Some(&exposure_logs));
+        let filtered = AtomFeed::from_logs(
+            &site,
+            &FeedFilter::default().with_component("ComCam"),
+            &[],
+            Some(&exposure_logs),
+        );
+        let xml = feed.render().unwrap();
+
+        assert_eq!(filtered.get_entries().len(), 0);
+        assert_eq!(
+            feed.get_entries()[0].get_id(),
+            "urn:uuid:000f68b2-e560-40ce-bdbc-a57b3363e1e9"
+        );
+        assert_eq!(feed.get_entries()[0].get_updated(), "2022-06-08T23:19:38Z");
+        assert!(xml.starts_with(r#"<?xml version="1.0" encoding="utf-8"?>"#));
+        assert!(xml.contains("<updated>2022-06-08T23:19:38Z</updated>"));
+        assert!(xml.contains("<name>slimleashma</name>"));
+        assert!(xml.contains("<title>Narrative and exposure log - summit</title>"));
+        assert!(xml.contains(r#"type="image/png""#));
+        assert!(!xml.contains("<trail>"));
+    }
+
+    #[test]
+    fn test_from_narrative_logs() {
+        let narrative_log_json = r#"{"id":"04be0aef-e22a-4742-a5c0-0dab847ec237","site_id":"summit","message_text":"M1M3 raised with errors","level":0,"tags":[],"urls":["https://example.org/m1m3.png"],"time_lost":1.5,"date_begin":"2024-08-14T01:00:00.000000","user_id":"observer","user_agent":"LOVE","is_human":true,"is_valid":true,"date_added":"2024-08-14T02:00:00.000000","date_invalidated":null,"parent_id":null,"systems":["Simonyi"],"subsystems":["M1M3"],"cscs":[],"date_end":"2024-08-14T02:30:00.000000","components":["Force Balance"],"primary_software_components":[],"primary_hardware_components":[],"category":"","time_lost_type":"fault"}"#;
+        let narrative_logs: Vec<NarrativeLog> = vec![
+            serde_json::from_str(narrative_log_json).unwrap(),
+            serde_json::from_str(&narrative_log_json.replace("2024-08-14T02:00:00.000000", ""))
+                .unwrap(),
+        ];
+        let site = Site::summit();
+
+        let feed = AtomFeed::from_logs(&site, &FeedFilter::default(), &narrative_logs, None);
+        let filtered = AtomFeed::from_logs(
+            &site,
+            &FeedFilter::default().with_component("M1M3"),
+            &narrative_logs,
+            None,
+        );
+        let xml = feed.render().unwrap();
+
+        assert_eq!(feed.get_entries().len(), 1);
+        assert_eq!(
+            feed.get_entries()[0].get_categories(),
+            ["Simonyi", "M1M3", "Force Balance"]
+        );
+        assert_eq!(filtered.get_entries().len(), 1);
+        assert_eq!(
+            AtomFeed::from_logs(
+                &site,
+                &FeedFilter::default().with_component("ATDome"),
+                &narrative_logs,
+                None
+            )
+            .get_entries()
+            .len(),
+            0
+        );
+        assert!(xml.contains(
+            r#"<link rel="enclosure" href="https://example.org/m1m3.png" type="image/png"/>"#
+        ));
+        assert!(!xml.contains(r#"rel="alternate""#));
+        assert!(xml.contains("<id>https://summit-lsp.lsst.codes/narrativelog/messages</id>"));
+        assert!(xml.contains("<title>Narrative log - summit</title>"));
+        assert_eq!(
+            filtered.id,
+            "https://summit-lsp.lsst.codes/narrativelog/messages?component=M1M3"
+        );
+        assert_eq!(filtered.title, "Narrative log - summit - M1M3");
+    }
+}
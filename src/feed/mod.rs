@@ -0,0 +1 @@
+pub mod atom_feed;
@@ -0,0 +1,379 @@
+use regex::Regex;
+use reqwest::StatusCode;
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    sync::OnceLock,
+};
+use thiserror::Error;
+use url::Url;
+
+use crate::{client::client::RolexClient, site::site::Site};
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorRetrievingJiraIssue(String);
+
+/// Number of issue keys looked up per JQL query.
+const BATCH_SIZE: usize = 50;
+
+/// Jira issue key, capturing the project key and the issue number.
+pub fn issue_key_regex() -> &'static Regex {
+    static ISSUE_KEY: OnceLock<Regex> = OnceLock::new();
+    ISSUE_KEY.get_or_init(|| Regex::new(r"\b([A-Z][A-Z0-9_]+)-([1-9][0-9]*)\b").unwrap())
+}
+
+/// Extract the Jira issue keys of `projects` referenced in `text`, in order
+/// of first appearance and without duplicates.
+pub fn extract_issue_keys(text: &str, projects: &[String]) -> Vec<String> {
+    let mut issue_keys: Vec<String> = Vec::new();
+
+    for captures in issue_key_regex().captures_iter(text) {
+        let is_known_project = projects.iter().any(|project| *project == captures[1]);
+        let issue_key = captures[0].to_owned();
+        if is_known_project && !issue_keys.contains(&issue_key) {
+            issue_keys.push(issue_key);
+        }
+    }
+    issue_keys
+}
+
+/// Jira issue keys of `projects` referenced in a log entry message or in
+/// its urls.
+pub fn extract_log_issue_keys(message: &str, urls: &[String], projects: &[String]) -> Vec<String> {
+    let mut text = message.to_owned();
+    for url in urls {
+        text.push(' ');
+        text.push_str(url);
+    }
+    extract_issue_keys(&text, projects)
+}
+
+/// The issues of `jira_issues` listed in `issue_keys`, in that order.
+pub fn select_jira_issues(
+    issue_keys: &[String],
+    jira_issues: &HashMap<String, JiraIssue>,
+) -> Vec<JiraIssue> {
+    issue_keys
+        .iter()
+        .filter_map(|issue_key| jira_issues.get(issue_key).cloned())
+        .collect()
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+struct Named {
+    name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+struct Assignee {
+    #[serde(rename = "displayName")]
+    display_name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+struct Fields {
+    summary: String,
+    status: Option<Named>,
+    priority: Option<Named>,
+    assignee: Option<Assignee>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+struct Issue {
+    key: String,
+    fields: Fields,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+struct SearchResult {
+    issues: Vec<Issue>,
+    #[serde(rename = "nextPageToken")]
+    next_page_token: Option<String>,
+}
+
+/// Current state of a Jira issue referenced from a log entry.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq)]
+pub struct JiraIssue {
+    key: String,
+    summary: String,
+    status: Option<String>,
+    priority: Option<String>,
+    assignee: Option<String>,
+    url: String,
+}
+
+impl JiraIssue {
+    pub fn get_key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn get_summary(&self) -> &str {
+        &self.summary
+    }
+
+    pub fn get_status(&self) -> &Option<String> {
+        &self.status
+    }
+
+    pub fn get_priority(&self) -> &Option<String> {
+        &self.priority
+    }
+
+    pub fn get_assignee(&self) -> &Option<String> {
+        &self.assignee
+    }
+
+    /// Link to the issue in the Jira web UI.
+    pub fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    /// Fetch `issue_keys` in batches through JQL, returning the issues that
+    /// exist keyed by issue key.
+    pub async fn retrieve(
+        client: &RolexClient,
+        site: &Site,
+        issue_keys: &[String],
+    ) -> Result<HashMap<String, JiraIssue>, Box<dyn Error>> {
+        JiraIssue::retrieve_matching(client, site, "key", issue_keys).await
+    }
+
+    /// Fetch the issues with numeric ids `issue_ids`, as linked from Zephyr
+    /// Scale, returning them keyed by issue key.
+    pub async fn retrieve_by_ids(
+        client: &RolexClient,
+        site: &Site,
+        issue_ids: &[usize],
+    ) -> Result<HashMap<String, JiraIssue>, Box<dyn Error>> {
+        let issue_ids: Vec<String> = issue_ids.iter().map(usize::to_string).collect();
+        JiraIssue::retrieve_matching(client, site, "id", &issue_ids).await
+    }
+
+    /// Search issues whose `field` is one of `values`, in batches.
+    ///
+    /// Jira rejects a whole JQL query when one of its values does not exist
+    /// or cannot be seen, so a rejected batch is retried one value at a time
+    /// and the rejected values are left out.
+    async fn retrieve_matching(
+        client: &RolexClient,
+        site: &Site,
+        field: &str,
+        values: &[String],
+    ) -> Result<HashMap<String, JiraIssue>, Box<dyn Error>> {
+        let values: BTreeSet<&String> = values.iter().collect();
+        let values: Vec<&String> = values.into_iter().collect();
+        let mut issues = HashMap::new();
+
+        for batch in values.chunks(BATCH_SIZE) {
+            let found = match JiraIssue::search_all(client, site, field, batch).await? {
+                Some(found) => found,
+                None if batch.len() > 1 => {
+                    let mut found = Vec::new();
+                    for value in batch {
+                        found.extend(
+                            JiraIssue::search_all(client, site, field, &[value])
+                                .await?
+                                .unwrap_or_default(),
+                        );
+                    }
+                    found
+                }
+                None => Vec::new(),
+            };
+            for issue in found {
+                let jira_issue = JiraIssue::from_issue(site, issue)?;
+                issues.insert(jira_issue.key.to_owned(), jira_issue);
+            }
+        }
+        Ok(issues)
+    }
+
+    /// Every page of the issues whose `field` is one of `values`, `None`
+    /// when Jira rejects the query.
+    async fn search_all(
+        client: &RolexClient,
+        site: &Site,
+        field: &str,
+        values: &[&String],
+    ) -> Result<Option<Vec<Issue>>, Box<dyn Error>> {
+        let jql = format!(
+            "{field} in ({})",
+            values
+                .iter()
+                .map(|value| value.as_str())
+                .collect::<Vec<&str>>()
+                .join(",")
+        );
+        let mut issues = Vec::new();
+        let mut next_page_token: Option<String> = None;
+
+        loop {
+            let Some(search_result) =
+                JiraIssue::search(client, site, &jql, next_page_token.as_deref()).await?
+            else {
+                return Ok(None);
+            };
+            issues.extend(search_result.issues);
+            next_page_token = search_result.next_page_token;
+            if next_page_token.is_none() {
+                return Ok(Some(issues));
+            }
+        }
+    }
+
+    /// One page of the issues matching `jql`, `None` when Jira rejects the
+    /// query as invalid.
+    async fn search(
+        client: &RolexClient,
+        site: &Site,
+        jql: &str,
+        next_page_token: Option<&str>,
+    ) -> Result<Option<SearchResult>, Box<dyn Error>> {
+        let authorization = client.get_jira_authorization().await?;
+        let mut url = Url::parse(site.get_jira_url())?.join("rest/api/3/search/jql")?;
+        url.query_pairs_mut()
+            .append_pair("jql", jql)
+            .append_pair("fields", "summary,status,priority,assignee")
+            .append_pair("maxResults", &BATCH_SIZE.to_string());
+        if let Some(next_page_token) = next_page_token {
+            url.query_pairs_mut()
+                .append_pair("nextPageToken", next_page_token);
+        }
+
+        let request = client
+            .get_http()
+            .get(url)
+            .header("Authorization", authorization)
+            .header("Accept", "application/json");
+        let response = client.send(request).await?;
+
+        if response.status() == StatusCode::BAD_REQUEST {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let response_text = response.text().await.unwrap_or_default();
+            return Err(Box::new(ErrorRetrievingJiraIssue(format!(
+                "Error: {status} {response_text}"
+            ))));
+        }
+
+        let response_text = response.text().await?;
+        Ok(Some(serde_json::from_str(&response_text)?))
+    }
+
+    fn from_issue(site: &Site, issue: Issue) -> Result<JiraIssue, Box<dyn Error>> {
+        let url = Url::parse(site.get_jira_url())?.join(&format!("browse/{}", issue.key))?;
+
+        Ok(JiraIssue {
+            key: issue.key,
+            summary: issue.fields.summary,
+            status: issue.fields.status.map(|status| status.name),
+            priority: issue.fields.priority.map(|priority| priority.name),
+            assignee: issue.fields.assignee.map(|assignee| assignee.display_name),
+            url: url.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::credentials::credentials::{
+        StaticCredentialProvider, JIRA_CLOUD_API_TOKEN, JIRA_CLOUD_EMAIL,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn test_extract_issue_keys() {
+        let projects = vec!["OBS".to_owned(), "SITCOM".to_owned()];
+        let text = "Dome fault again, see OBS-123 and SITCOM-456 (dup of OBS-123). UTF-8 and DM-1 are ignored, so is XOBS-12.";
+
+        assert_eq!(
+            extract_issue_keys(text, &projects),
+            vec!["OBS-123".to_owned(), "SITCOM-456".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_from_issue() {
+        let search_result_json = r#"{"issues":[{"id":"10001","key":"OBS-123","fields":{"summary":"ATDome does not close","status":{"name":"In Progress"},"priority":{"name":"High"},"assignee":null}}],"isLast":true}"#;
+
+        let search_result: SearchResult = serde_json::from_str(search_result_json).unwrap();
+        let issue = search_result.issues.into_iter().next().unwrap();
+        let jira_issue = JiraIssue::from_issue(&Site::summit(), issue).unwrap();
+
+        assert_eq!(jira_issue.get_key(), "OBS-123");
+        assert_eq!(jira_issue.get_status(), &Some("In Progress".to_owned()));
+        assert_eq!(jira_issue.get_assignee(), &None);
+        assert_eq!(
+            jira_issue.get_url(),
+            "https://rubinobs.atlassian.net/browse/OBS-123"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_skips_unknown_keys() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = format!(
+            r#"{{"local":{{"name":"local","efd_name":"summit_efd","narrative_log_url":"https://summit-lsp.lsst.codes/narrativelog/messages","exposure_log_url":"https://summit-lsp.lsst.codes/exposurelog/messages","zephyr_url":"https://api.zephyrscale.smartbear.com/v2/","jira_url":"http://{}/","rubintv_url":"https://storage.googleapis.com/rubintv_data/"}}}}"#,
+            listener.local_addr().unwrap()
+        );
+        let site = Site::load_all_from_str(&config)
+            .unwrap()
+            .remove("local")
+            .unwrap();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for _ in 0..3 {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = [0; 4096];
+                let read = stream.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+                let response = if request.contains("OBS-999") && request.contains("OBS-123") {
+                    let body = r#"{"errorMessages":["An issue with key 'OBS-999' does not exist for field 'key'."]}"#;
+                    format!("HTTP/1.1 400 Bad Request\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len())
+                } else if request.contains("OBS-999") {
+                    "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_owned()
+                } else {
+                    let body = r#"{"issues":[{"id":"10001","key":"OBS-123","fields":{"summary":"ATDome does not close","status":{"name":"In Progress"},"priority":null,"assignee":null}}]}"#;
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                };
+                stream.write_all(response.as_bytes()).await.unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        let client = RolexClient::new().with_credential_provider(Box::new(
+            StaticCredentialProvider::new(&[
+                (JIRA_CLOUD_EMAIL, "observer@lsst.org"),
+                (JIRA_CLOUD_API_TOKEN, "jira-token"),
+            ]),
+        ));
+
+        let issues = JiraIssue::retrieve(
+            &client,
+            &site,
+            &["OBS-123".to_owned(), "OBS-999".to_owned()],
+        )
+        .await
+        .unwrap();
+        let requests = server.await.unwrap();
+
+        assert_eq!(requests.len(), 3);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues["OBS-123"].get_status(),
+            &Some("In Progress".to_owned())
+        );
+    }
+}
@@ -0,0 +1,2 @@
+pub mod jira;
+pub mod new_issue;
@@ -0,0 +1,292 @@
+use serde_json::{json, Value};
+use std::{collections::HashMap, error::Error};
+use thiserror::Error;
+use url::Url;
+
+use crate::{
+    client::client::RolexClient, fault_log::fault_log::FaultLog,
+    narrative_log::narrative_log::NarrativeLog, site::site::Site,
+};
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorCreatingJiraIssue(String);
+
+/// Jira limits summaries to 255 characters.
+const MAX_SUMMARY_LENGTH: usize = 255;
+
+#[derive(Debug, Deserialize)]
+struct CreatedIssue {
+    key: String,
+}
+
+/// Jira issue to be created from a fault episode or a narrative entry.
+///
+/// Use [`NewJiraIssue::to_payload`] to preview what would be sent before
+/// calling [`NewJiraIssue::create`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct NewJiraIssue {
+    project_key: String,
+    issue_type: String,
+    summary: String,
+    description: String,
+    priority: Option<String>,
+    components: Vec<String>,
+    labels: Vec<String>,
+    urls: Vec<String>,
+}
+
+impl NewJiraIssue {
+    pub fn new(project_key: &str, summary: &str) -> NewJiraIssue {
+        NewJiraIssue {
+            project_key: project_key.to_owned(),
+            issue_type: "Bug".to_owned(),
+            summary: NewJiraIssue::truncate_summary(summary),
+            ..NewJiraIssue::default()
+        }
+    }
+
+    /// Issue for a Watcher alarm, with the alarm severity mapped to the
+    /// issue priority.
+    pub fn from_fault_log(project_key: &str, fault_log: &FaultLog) -> NewJiraIssue {
+        let summary = format!("{}: {}", fault_log.get_name(), fault_log.get_reason());
+        let priority = match fault_log.get_severity() {
+            4 => Some("Highest"),
+            3 => Some("High"),
+            2 => Some("Medium"),
+            _ => None,
+        };
+
+        let mut new_issue = NewJiraIssue::new(project_key, &summary);
+        new_issue.description = format!(
+            "Watcher alarm *{}* raised at {} with severity {}.\n\n{}",
+            fault_log.get_name(),
+            fault_log.get_time(),
+            fault_log.get_severity(),
+            fault_log.get_reason(),
+        );
+        new_issue.priority = priority.map(|priority| priority.to_owned());
+        new_issue.labels = vec!["watcher".to_owned()];
+        new_issue
+    }
+
+    /// Issue for a narrative log entry. Subsystems and components become
+    /// Jira components, systems become labels.
+    pub fn from_narrative_log(project_key: &str, narrative_log: &NarrativeLog) -> NewJiraIssue {
+        let message_text = narrative_log.get_message_text().trim();
+        let plain_text = narrative_log.get_message_plain_text();
+        let summary = plain_text.lines().next().unwrap_or_default();
+
+        let mut description = format!(
+            "Reported by {} on {}.\nFrom {} to {}",
+            narrative_log.get_user_id(),
+            narrative_log.get_site_id(),
+            narrative_log.get_date_begin(),
+            narrative_log.get_date_end(),
+        );
+        if narrative_log.get_time_lost() > 0.0 {
+            description.push_str(&format!(", {} hours lost", narrative_log.get_time_lost()));
+        }
+        description.push_str(&format!(
+            ".\n\n{message_text}\n\nNarrative log entry: {}",
+            narrative_log.get_id()
+        ));
+
+        let mut components: Vec<String> = Vec::new();
+        for names in [
+            narrative_log.get_subsystems(),
+            narrative_log.get_components(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            for name in names {
+                if !components.contains(name) {
+                    components.push(name.to_owned());
+                }
+            }
+        }
+
+        let mut new_issue = NewJiraIssue::new(project_key, summary);
+        new_issue.description = description;
+        new_issue.components = components;
+        new_issue.labels = narrative_log
+            .get_systems()
+            .iter()
+            .flatten()
+            .map(|system| system.replace(char::is_whitespace, "_"))
+            .collect();
+        new_issue.urls = narrative_log.get_urls().to_vec();
+        new_issue
+    }
+
+    fn truncate_summary(summary: &str) -> String {
+        let summary = summary.trim();
+        if summary.chars().count() <= MAX_SUMMARY_LENGTH {
+            summary.to_owned()
+        } else {
+            let truncated: String = summary.chars().take(MAX_SUMMARY_LENGTH - 3).collect();
+            format!("{truncated}...")
+        }
+    }
+
+    pub fn with_issue_type(mut self, issue_type: &str) -> NewJiraIssue {
+        self.issue_type = issue_type.to_owned();
+        self
+    }
+
+    pub fn with_priority(mut self, priority: &str) -> NewJiraIssue {
+        self.priority = Some(priority.to_owned());
+        self
+    }
+
+    /// Rename components to their Jira names, dropping those with no
+    /// entry in `component_map`.
+    pub fn with_component_map(mut self, component_map: &HashMap<String, String>) -> NewJiraIssue {
+        self.components = self
+            .components
+            .iter()
+            .filter_map(|component| component_map.get(component).cloned())
+            .collect();
+        self
+    }
+
+    pub fn with_url(mut self, url: &str) -> NewJiraIssue {
+        self.urls.push(url.to_owned());
+        self
+    }
+
+    pub fn get_summary(&self) -> &str {
+        &self.summary
+    }
+
+    pub fn get_components(&self) -> &[String] {
+        &self.components
+    }
+
+    /// Body of the `POST rest/api/2/issue` request, usable as a dry run.
+    /// Fails when the summary is empty, as Jira requires one.
+    pub fn to_payload(&self) -> Result<Value, ErrorCreatingJiraIssue> {
+        if self.summary.trim().is_empty() {
+            return Err(ErrorCreatingJiraIssue(
+                "Error: the issue summary is empty".to_owned(),
+            ));
+        }
+
+        let mut description = self.description.to_owned();
+        if !self.urls.is_empty() {
+            description.push_str("\n\nAttached:");
+            for url in &self.urls {
+                description.push_str(&format!("\n* {url}"));
+            }
+        }
+
+        let mut fields = json!({
+            "project": {"key": self.project_key},
+            "issuetype": {"name": self.issue_type},
+            "summary": self.summary,
+            "description": description,
+            "labels": self.labels,
+            "components": self
+                .components
+                .iter()
+                .map(|component| json!({"name": component}))
+                .collect::<Vec<Value>>(),
+        });
+        if let Some(priority) = &self.priority {
+            fields["priority"] = json!({"name": priority});
+        }
+
+        Ok(json!({ "fields": fields }))
+    }
+
+    /// Create the issue and link the attached urls, returning the issue key
+    /// with the urls that could not be linked.
+    ///
+    /// Once the issue exists its key is always returned, so that a failed
+    /// link never leads the caller to file the issue again.
+    pub async fn create(
+        &self,
+        client: &RolexClient,
+        site: &Site,
+    ) -> Result<(String, Vec<ErrorCreatingJiraIssue>), Box<dyn Error>> {
+        let payload = self.to_payload()?;
+        let url = Url::parse(site.get_jira_url())?.join("rest/api/2/issue")?;
+        let authorization = client.get_jira_authorization().await?;
+        let created_issue: CreatedIssue = client
+            .send_json(
+                client.get_http().post(url),
+                Some(authorization),
+                Some(&payload),
+            )
+            .await?;
+
+        let mut link_errors = Vec::new();
+        let remote_link_url = match Url::parse(site.get_jira_url()).and_then(|jira_url| {
+            jira_url.join(&format!(
+                "rest/api/2/issue/{}/remotelink",
+                created_issue.key
+            ))
+        }) {
+            Ok(remote_link_url) => remote_link_url,
+            Err(error) => {
+                link_errors.push(ErrorCreatingJiraIssue(format!(
+                    "Could not link urls: {error}"
+                )));
+                return Ok((created_issue.key, link_errors));
+            }
+        };
+        for url in &self.urls {
+            let remote_link = json!({"object": {"url": url, "title": url}});
+            if let Err(error) = client
+                .send_json::<Value>(
+                    client.get_http().post(remote_link_url.clone()),
+                    Some(authorization),
+                    Some(&remote_link),
+                )
+                .await
+            {
+                link_errors.push(ErrorCreatingJiraIssue(format!(
+                    "Could not link {url}: {error}"
+                )));
+            }
+        }
+
+        Ok((created_issue.key, link_errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_from_narrative_log() {
+        let narrative_log_json = r#"{"id":"04be0aef-e22a-4742-a5c0-0dab847ec237","site_id":"summit","message_text":"M1M3 raised with errors\nForce balance faulted twice.","level":0,"tags":[],"urls":["https://rubinobs.atlassian.net/browse/OBS-100"],"time_lost":1.5,"date_begin":"2024-08-14T01:00:00.000000","user_id":"observer","user_agent":"LOVE","is_human":true,"is_valid":true,"date_added":"2024-08-14T02:00:00.000000","date_invalidated":null,"parent_id":null,"systems":["Simonyi Main Telescope"],"subsystems":["M1M3"],"cscs":[],"date_end":"2024-08-14T02:30:00.000000","components":["M1M3","Force Balance"],"primary_software_components":[],"primary_hardware_components":[],"category":"","time_lost_type":"fault"}"#;
+        let narrative_log: NarrativeLog = serde_json::from_str(narrative_log_json).unwrap();
+
+        let payload = NewJiraIssue::from_narrative_log("OBS", &narrative_log)
+            .to_payload()
+            .unwrap();
+
+        assert_eq!(payload["fields"]["project"]["key"], "OBS");
+        assert_eq!(payload["fields"]["summary"], "M1M3 raised with errors");
+        assert_eq!(
+            payload["fields"]["components"],
+            json!([{"name": "M1M3"}, {"name": "Force Balance"}])
+        );
+        assert_eq!(
+            payload["fields"]["labels"],
+            json!(["Simonyi_Main_Telescope"])
+        );
+        let description = payload["fields"]["description"].as_str().unwrap();
+        assert!(description.contains("1.5 hours lost"));
+        assert!(description.contains("* https://rubinobs.atlassian.net/browse/OBS-100"));
+    }
+
+    #[test]
+    fn test_empty_summary() {
+        assert!(NewJiraIssue::new("OBS", "  ").to_payload().is_err());
+    }
+}
@@ -1,7 +1,24 @@
 #[macro_use]
 extern crate serde_derive;
 pub mod block_log;
+pub mod calendar;
+pub mod client;
+pub mod confluence;
+pub mod credentials;
+pub mod efd;
+pub mod email;
+pub mod export;
 pub mod exposure_log;
 pub mod fault_log;
+pub mod feed;
+pub mod jira;
+pub mod markdown;
 pub mod narrative_log;
+pub mod night_fetcher;
 pub mod night_plan;
+pub mod night_report;
+pub mod night_report_page;
+pub mod night_summary;
+pub mod site;
+pub mod slack;
+pub mod timeline;
@@ -1,98 +1,97 @@
-mod exposure_log;
-mod fault_log;
-mod narrative_log;
-
-#[macro_use]
-extern crate serde_derive;
-
-use rolex::block_log::block_log::BlockLog;
+use askama::Template;
+use rolex::calendar::icalendar::ICalendar;
+use rolex::client::client::RolexClient;
+use rolex::export::export::{ExportFormat, ExportTable, ExportWriter};
+use rolex::night_fetcher::night_fetcher::NightFetcher;
 use rolex::night_plan::night_plan::NightPlan;
-use std::{collections::HashMap, error::Error};
-use url::Url;
-
-use crate::{
-    exposure_log::exposure_log::ExposureLog, fault_log::fault_log::FaultLog,
-    narrative_log::narrative_log::NarrativeLog,
+use rolex::night_plan::zephyr::ZephyrCache;
+use rolex::night_report_page::night_report_page::NightReportPage;
+use rolex::night_summary::night_summary::NightSummary;
+use rolex::site::site::Site;
+use std::{
+    env,
+    error::Error,
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
 };
+
 use chrono;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    /*
-    let base_url = "https://tucson-teststand.lsst.codes/exposurelog/messages";
-    let mut url = Url::parse(base_url).unwrap();
-
-    // Add query parameters
-    url.query_pairs_mut()
-        .append_pair("min_date_added", "2023-09-13T00:00:00.000000")
-        .append_pair("limit", "2");
-    println!("{}", url);
-    let response = reqwest::get(&url.to_string()).await?;
-
-    let response_text = response.text().await.unwrap();
-
-    println!("{response_text}");
-    let exposure_logs: Vec<ExposureLog> = serde_json::from_str(&response_text).unwrap();
-
-    println!("Got {} entries: {:?}", exposure_logs.len(), exposure_logs);
-
-    let params = Some(HashMap::from([
-        (
-            "min_date_added".to_string(),
-            "2023-09-13T00:00:00.000000".to_string(),
-        ),
-        ("limit".to_string(), "2".to_string()),
-    ]));
-    let exposure_logs = ExposureLog::retrieve(&base_url, &params).await?;
-
-    println!("Got {} entries: {:?}", exposure_logs.len(), exposure_logs);
-
-    let base_url = "https://tucson-teststand.lsst.codes/narrativelog/messages";
-    let mut url = Url::parse(base_url).unwrap();
+    let site_name = env::args().nth(1).unwrap_or("summit".to_owned());
+    let site = match env::var("ROLEX_SITE_CONFIG") {
+        Ok(config) => Site::load(Path::new(&config), &site_name)?,
+        Err(_) => Site::from_name(&site_name)?,
+    };
 
-    // Add query parameters
-    url.query_pairs_mut().append_pair("limit", "2");
-    println!("{}", url);
-    let response = reqwest::get(&url.to_string()).await?;
+    let client = RolexClient::new();
 
-    let response_text = response.text().await.unwrap();
-
-    println!("{response_text}");
-    let narrative_logs: Vec<NarrativeLog> = serde_json::from_str(&response_text).unwrap();
-
-    println!("Got {} entries: {:?}", narrative_logs.len(), narrative_logs);
-
-    let params = Some(HashMap::from([("limit".to_string(), "2".to_string())]));
-    let narrative_logs = NarrativeLog::retrieve(base_url, &params).await?;
-    println!("Got {} entries: {:?}", narrative_logs.len(), narrative_logs);
-
-    // let base_url: &str = "https://api.zephyrscale.smartbear.com/v2/";
-    // let test_cycle_key: &str = "BLOCK-R19";
-
-    // let night_plan = NightPlan::retrieve(base_url, test_cycle_key).await?;
-    // println!("NightPlan = {night_plan:?}");
-
-    // let night_plan_status = night_plan.get_status().await?;
-    // println!("{night_plan_status}");
-
-    // let night_plan_owner = night_plan.get_owner().await;
-    // println!("{night_plan_owner:?}");
-
-    // let night_plan_links = night_plan.get_links().await?;
-    // println!("{night_plan_links}");
-    */
     let parse_from_str = chrono::NaiveDateTime::parse_from_str;
 
     let date_start = parse_from_str("2024-08-13T12:00:00", "%Y-%m-%dT%H:%M:%S")?;
     let date_end = date_start + chrono::Duration::days(1);
     println!("{date_start:?} {date_end:?}");
 
-    let fault_logs = FaultLog::retrieve(&date_start, &date_end).await;
-
-    println!("{fault_logs:?}");
-
-    let block_logs = BlockLog::retrieve("summit_efd", &date_start, &date_end).await;
+    let day_obs: usize = date_start.format("%Y%m%d").to_string().parse()?;
+    let zephyr_cache = ZephyrCache::new();
+
+    let mut night_fetcher = NightFetcher::new(&client, &site);
+    match NightPlan::find_for_day_obs(&client, &site, &zephyr_cache, "BLOCK", day_obs).await {
+        Ok(night_plans) => match night_plans.first() {
+            Some(night_plan) => night_fetcher = night_fetcher.with_night_plan(night_plan.get_key()),
+            None => println!("No night plan found for {day_obs}"),
+        },
+        Err(error) => println!("Could not find the night plan for {day_obs}: {error}"),
+    }
+    let mut night_data = night_fetcher.fetch(&date_start, &date_end).await;
+
+    if let Err(error) = night_data.resolve_jira_issues(&client, &site).await {
+        println!("Could not resolve Jira issues: {error}");
+    }
+
+    for status in night_data.get_statuses() {
+        println!(
+            "{}: {:?} in {:?}",
+            status.get_source(),
+            status.get_outcome(),
+            status.get_duration()
+        );
+    }
+
+    let page = NightReportPage::from_night_data(&site, day_obs, &night_data)?.render()?;
+    fs::write(format!("night_report_{day_obs}.html"), page)?;
+
+    let width = env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(80);
+    let summary = NightSummary::new(&site, day_obs, &night_data).with_width(width);
+    fs::write(format!("night_report_{day_obs}.md"), summary.to_markdown())?;
+    println!("{}", summary.to_text());
+
+    let mut calendar = ICalendar::new(&format!("Observing - {}", site.get_name()))
+        .with_block_logs(&site, night_data.get_block_logs());
+    if let Some(night_plan) = night_data.get_night_plan() {
+        let owner = night_plan.get_owner(&client, &zephyr_cache).await.ok();
+        calendar = calendar.with_night_plan(&site, night_plan, owner.as_ref());
+    }
+    fs::write(format!("night_report_{day_obs}.ics"), calendar.to_ics())?;
+
+    if let Ok(format) = env::var("ROLEX_EXPORT_FORMAT") {
+        let format = ExportFormat::from_name(&format)?;
+        for table in ExportTable::ALL {
+            let path = format!(
+                "night_report_{day_obs}_{}.{}",
+                table.get_name(),
+                format.get_extension()
+            );
+            let mut writer = ExportWriter::new(format, table, BufWriter::new(File::create(path)?))?;
+            writer.write_night(&night_data)?;
+            writer.finish()?;
+        }
+    }
 
-    println!("{block_logs:?}");
     Ok(())
 }
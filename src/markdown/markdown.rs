@@ -0,0 +1,175 @@
+use pulldown_cmark::{html, CowStr, Event, LinkType, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::{jira::jira::issue_key_regex, site::site::Site};
+
+fn auto_link_regex() -> &'static Regex {
+    static AUTO_LINK: OnceLock<Regex> = OnceLock::new();
+    AUTO_LINK.get_or_init(|| {
+        Regex::new(&format!(
+            r"(?P<url>https?://[^\s<>()\[\]]*[^\s<>()\[\].,;:!?'])|\b(?P<obs_id>(?:AT|CC|MC|TS)_[A-Z]_[0-9]{{8}}_[0-9]{{6}})\b|(?P<issue_key>{})",
+            issue_key_regex().as_str()
+        ))
+        .unwrap()
+    })
+}
+
+fn get_options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS
+}
+
+fn link(dest_url: String, text: String) -> [Event<'static>; 3] {
+    [
+        Event::Start(Tag::Link {
+            link_type: LinkType::Inline,
+            dest_url: CowStr::from(dest_url),
+            title: CowStr::from(""),
+            id: CowStr::from(""),
+        }),
+        Event::Text(CowStr::from(text)),
+        Event::End(TagEnd::Link),
+    ]
+}
+
+/// Split a text event into text and links for the bare URLs, Jira issue
+/// keys of the site projects and obs_ids it contains.
+fn auto_link(text: &str, site: &Site) -> Vec<Event<'static>> {
+    let mut events = Vec::new();
+    let mut last = 0;
+
+    for captures in auto_link_regex().captures_iter(text) {
+        let matched = captures.get(0).unwrap();
+        let dest_url = if let Some(url) = captures.name("url") {
+            url.as_str().to_owned()
+        } else if let Some(obs_id) = captures.name("obs_id") {
+            format!("{}?obs_id={}", site.get_exposure_log_url(), obs_id.as_str())
+        } else {
+            let issue_key = matched.as_str();
+            let project = issue_key.split('-').next().unwrap_or_default();
+            if !site
+                .get_jira_projects()
+                .iter()
+                .any(|known| known == project)
+            {
+                continue;
+            }
+            format!("{}browse/{issue_key}", site.get_jira_url())
+        };
+
+        if matched.start() > last {
+            events.push(Event::Text(CowStr::from(
+                text[last..matched.start()].to_owned(),
+            )));
+        }
+        events.extend(link(dest_url, matched.as_str().to_owned()));
+        last = matched.end();
+    }
+
+    if last < text.len() {
+        events.push(Event::Text(CowStr::from(text[last..].to_owned())));
+    }
+    events
+}
+
+/// Render operator Markdown to sanitized HTML, linking URLs, Jira keys and
+/// obs_ids that are not already inside a link or code.
+pub fn render_html(text: &str, site: &Site) -> String {
+    let mut events: Vec<Event> = Vec::new();
+    let mut depth = 0;
+
+    for event in Parser::new_ext(text, get_options()) {
+        match event {
+            Event::Start(Tag::Link { .. } | Tag::Image { .. } | Tag::CodeBlock(_)) => {
+                depth += 1;
+                events.push(event);
+            }
+            Event::End(TagEnd::Link | TagEnd::Image | TagEnd::CodeBlock) => {
+                depth -= 1;
+                events.push(event);
+            }
+            Event::Text(text) if depth == 0 => events.extend(auto_link(&text, site)),
+            _ => events.push(event),
+        }
+    }
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events.into_iter());
+
+    ammonia::Builder::default()
+        .link_rel(Some("noopener noreferrer"))
+        .clean(&unsafe_html)
+        .to_string()
+}
+
+/// Render operator Markdown as plain text, for outputs that cannot show
+/// HTML. Link targets are kept after the link text.
+pub fn render_plain_text(text: &str) -> String {
+    let mut plain_text = String::new();
+    let mut links: Vec<String> = Vec::new();
+
+    for event in Parser::new_ext(text, get_options()) {
+        match event {
+            Event::Text(text) | Event::Code(text) => plain_text.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => plain_text.push('\n'),
+            Event::TaskListMarker(checked) => {
+                plain_text.push_str(if checked { "[x] " } else { "[ ] " })
+            }
+            Event::Start(Tag::Item) => plain_text.push_str("- "),
+            Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) => {
+                links.push(dest_url.to_string())
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                let dest_url = links.pop().unwrap_or_default();
+                if !plain_text.ends_with(&dest_url) {
+                    plain_text.push_str(&format!(" ({dest_url})"));
+                }
+            }
+            Event::End(TagEnd::TableCell) => plain_text.push('\t'),
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::CodeBlock
+                | TagEnd::Item
+                | TagEnd::TableHead
+                | TagEnd::TableRow,
+            ) if !plain_text.ends_with('\n') => plain_text.push('\n'),
+            _ => {}
+        }
+    }
+    plain_text.trim_end().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_render_html() {
+        let text = "Dome stuck, see OBS-123 and AT_O_20240813_000123.\n\n- https://rubinobs.org/x.\n- `OBS-124` not linked, nor FOO-1\n\n<script>alert(1)</script>";
+
+        let html = render_html(text, &Site::summit());
+
+        assert!(html.contains(
+            r#"<a href="https://rubinobs.atlassian.net/browse/OBS-123" rel="noopener noreferrer">OBS-123</a>"#
+        ));
+        assert!(html.contains(r#"exposurelog/messages?obs_id=AT_O_20240813_000123""#));
+        assert!(html.contains(r#"<a href="https://rubinobs.org/x" rel="noopener noreferrer">https://rubinobs.org/x</a>."#));
+        assert!(html.contains("<li>"));
+        assert!(html.contains("<code>OBS-124</code>"));
+        assert!(!html.contains("browse/FOO-1"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_render_plain_text() {
+        let text =
+            "# Summary\n\nSee [the log](https://rubinobs.org/log) and `code`.\n\n- one\n- two";
+
+        assert_eq!(
+            render_plain_text(text),
+            "Summary\nSee the log (https://rubinobs.org/log) and code.\n- one\n- two"
+        );
+    }
+}
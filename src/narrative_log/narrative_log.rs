@@ -2,6 +2,14 @@ use askama::Template;
 use std::{collections::HashMap, error::Error};
 use url::Url;
 
+use crate::{
+    client::client::RolexClient,
+    jira::jira::{extract_log_issue_keys, select_jira_issues, JiraIssue},
+    markdown::markdown,
+    site::site::Site,
+    timeline::timeline::Filterable,
+};
+
 #[derive(Debug, Deserialize, Serialize, Default, Template)]
 #[template(path = "log_entry.html", ext = "html")]
 pub struct NarrativeLog {
@@ -29,13 +37,92 @@ pub struct NarrativeLog {
     primary_hardware_components: Vec<String>,
     category: String,
     time_lost_type: Option<String>,
+    #[serde(skip)]
+    jira_issues: Vec<JiraIssue>,
 }
 
 impl NarrativeLog {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_site_id(&self) -> &str {
+        &self.site_id
+    }
+
+    pub fn get_message_text(&self) -> &str {
+        &self.message_text
+    }
+
+    /// Built-in profile of the site the entry was logged at.
+    pub fn get_site(&self) -> Option<Site> {
+        Site::from_name(&self.site_id).ok()
+    }
+
+    /// Message rendered from Markdown to sanitized HTML.
+    pub fn get_message_html(&self, site: &Site) -> String {
+        markdown::render_html(&self.message_text, site)
+    }
+
+    /// Message with the Markdown markup removed.
+    pub fn get_message_plain_text(&self) -> String {
+        markdown::render_plain_text(&self.message_text)
+    }
+
+    pub fn get_urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    pub fn get_time_lost(&self) -> f32 {
+        self.time_lost
+    }
+
+    pub fn get_date_begin(&self) -> &str {
+        &self.date_begin
+    }
+
+    pub fn get_date_end(&self) -> &str {
+        &self.date_end
+    }
+
+    pub fn get_user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    pub fn get_systems(&self) -> &Option<Vec<String>> {
+        &self.systems
+    }
+
+    pub fn get_subsystems(&self) -> &Option<Vec<String>> {
+        &self.subsystems
+    }
+
+    pub fn get_components(&self) -> &Option<Vec<String>> {
+        &self.components
+    }
+
     pub fn get_date_added(&self) -> &str {
         &self.date_added
     }
 
+    /// Jira issue keys of `projects` referenced in the message or urls.
+    pub fn get_issue_keys(&self, projects: &[String]) -> Vec<String> {
+        extract_log_issue_keys(&self.message_text, &self.urls, projects)
+    }
+
+    /// Keep the issues referenced by this entry out of `jira_issues`.
+    pub fn attach_jira_issues(
+        &mut self,
+        jira_issues: &HashMap<String, JiraIssue>,
+        projects: &[String],
+    ) {
+        self.jira_issues = select_jira_issues(&self.get_issue_keys(projects), jira_issues);
+    }
+
+    pub fn get_jira_issues(&self) -> &[JiraIssue] {
+        &self.jira_issues
+    }
+
     pub fn get_labels(&self) -> Vec<String> {
         self.components.clone().unwrap_or(vec!["None".to_owned()])
     }
@@ -50,6 +137,11 @@ impl NarrativeLog {
             "".to_string()
         }
     }
+
+    pub fn get_level(&self) -> usize {
+        self.level
+    }
+
     pub fn get_attached_images(&self) -> Vec<String> {
         self.urls
             .iter()
@@ -63,11 +155,12 @@ impl NarrativeLog {
             .collect()
     }
     pub async fn retrieve(
-        url: &str,
+        client: &RolexClient,
+        site: &Site,
         params: &Option<HashMap<String, String>>,
     ) -> Result<Vec<NarrativeLog>, Box<dyn Error>> {
         let url = {
-            let mut url = Url::parse(url)?;
+            let mut url = Url::parse(site.get_narrative_log_url())?;
 
             if let Some(params) = params {
                 for (key, value) in params {
@@ -77,7 +170,7 @@ impl NarrativeLog {
             url
         };
 
-        let response = reqwest::get(&url.to_string()).await?;
+        let response = client.send(client.get_http().get(url)).await?;
 
         let response_text = response.text().await?;
 
@@ -87,6 +180,21 @@ impl NarrativeLog {
     }
 }
 
+impl Filterable for NarrativeLog {
+    fn get_filter_attributes(&self) -> Vec<(&'static str, String)> {
+        let mut attributes = vec![("source", "NarrativeLog".to_owned())];
+        if let Some(systems) = &self.systems {
+            attributes.push(("system", systems.join("|")));
+        }
+        if let Some(components) = &self.components {
+            attributes.push(("component", components.join("|")));
+        }
+        attributes.push(("severity", self.level.to_string()));
+        attributes.push(("user", self.user_id.to_owned()));
+        attributes
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
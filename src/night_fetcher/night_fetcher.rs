@@ -0,0 +1,368 @@
+use chrono::NaiveDateTime;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    future::Future,
+    time::{Duration, Instant},
+};
+use tokio::{sync::Semaphore, time::timeout};
+
+use crate::{
+    block_log::block_log::BlockLog, client::client::RolexClient,
+    exposure_log::exposure_log::ExposureLog, fault_log::fault_log::FaultLog, jira::jira::JiraIssue,
+    narrative_log::narrative_log::NarrativeLog, night_plan::night_plan::NightPlan,
+    site::site::Site,
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.6f";
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub enum Source {
+    NarrativeLog,
+    ExposureLog,
+    FaultLog,
+    BlockLog,
+    NightPlan,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Source::NarrativeLog => "narrative log",
+            Source::ExposureLog => "exposure log",
+            Source::FaultLog => "fault log",
+            Source::BlockLog => "block log",
+            Source::NightPlan => "night plan",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum SourceOutcome {
+    Ok(usize),
+    Empty,
+    Failed(String),
+    TimedOut,
+}
+
+/// How fetching a single source went.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SourceStatus {
+    source: Source,
+    outcome: SourceOutcome,
+    duration: Duration,
+}
+
+impl SourceStatus {
+    pub fn get_source(&self) -> Source {
+        self.source
+    }
+
+    pub fn get_outcome(&self) -> &SourceOutcome {
+        &self.outcome
+    }
+
+    pub fn get_duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn is_ok(&self) -> bool {
+        matches!(self.outcome, SourceOutcome::Ok(_) | SourceOutcome::Empty)
+    }
+}
+
+/// Everything retrieved for one night, along with the status of each
+/// source. Sources that failed are left empty.
+#[derive(Debug, Default)]
+pub struct NightData {
+    narrative_logs: Vec<NarrativeLog>,
+    exposure_logs: Vec<ExposureLog>,
+    fault_logs: Vec<FaultLog>,
+    block_logs: Vec<BlockLog>,
+    night_plan: Option<NightPlan>,
+    jira_issues: HashMap<String, JiraIssue>,
+    statuses: Vec<SourceStatus>,
+}
+
+impl NightData {
+    pub fn get_narrative_logs(&self) -> &[NarrativeLog] {
+        &self.narrative_logs
+    }
+
+    pub fn get_exposure_logs(&self) -> &[ExposureLog] {
+        &self.exposure_logs
+    }
+
+    pub fn get_fault_logs(&self) -> &[FaultLog] {
+        &self.fault_logs
+    }
+
+    pub fn get_block_logs(&self) -> &[BlockLog] {
+        &self.block_logs
+    }
+
+    pub fn get_night_plan(&self) -> Option<&NightPlan> {
+        self.night_plan.as_ref()
+    }
+
+    pub fn get_statuses(&self) -> &[SourceStatus] {
+        &self.statuses
+    }
+
+    /// Short human readable status of `source`, e.g. "12 entries".
+    pub fn describe_status(&self, source: Source) -> String {
+        let Some(status) = self
+            .statuses
+            .iter()
+            .find(|status| status.get_source() == source)
+        else {
+            return "not fetched".to_owned();
+        };
+
+        match status.get_outcome() {
+            SourceOutcome::Ok(count) => format!("{count} entries"),
+            SourceOutcome::Empty => "no entries".to_owned(),
+            SourceOutcome::Failed(error) => format!("failed: {error}"),
+            SourceOutcome::TimedOut => "timed out".to_owned(),
+        }
+    }
+
+    /// Issues referenced by the night, keyed by issue key.
+    pub fn get_jira_issues(&self) -> &HashMap<String, JiraIssue> {
+        &self.jira_issues
+    }
+
+    /// Look up every Jira issue referenced by the narrative and exposure
+    /// logs or linked to the night plan, and attach them to the entries.
+    pub async fn resolve_jira_issues(
+        &mut self,
+        client: &RolexClient,
+        site: &Site,
+    ) -> Result<(), Box<dyn Error>> {
+        let projects = site.get_jira_projects();
+
+        let mut issue_keys: Vec<String> = Vec::new();
+        for narrative_log in &self.narrative_logs {
+            issue_keys.extend(narrative_log.get_issue_keys(projects));
+        }
+        for exposure_log in &self.exposure_logs {
+            issue_keys.extend(exposure_log.get_issue_keys(projects));
+        }
+        let issue_ids = match &self.night_plan {
+            Some(night_plan) => night_plan.get_issue_ids(),
+            None => Vec::new(),
+        };
+        if issue_keys.is_empty() && issue_ids.is_empty() {
+            return Ok(());
+        }
+
+        self.jira_issues = JiraIssue::retrieve(client, site, &issue_keys).await?;
+        if !issue_ids.is_empty() {
+            self.jira_issues
+                .extend(JiraIssue::retrieve_by_ids(client, site, &issue_ids).await?);
+        }
+        for narrative_log in &mut self.narrative_logs {
+            narrative_log.attach_jira_issues(&self.jira_issues, projects);
+        }
+        for exposure_log in &mut self.exposure_logs {
+            exposure_log.attach_jira_issues(&self.jira_issues, projects);
+        }
+        Ok(())
+    }
+}
+
+/// Fetch all sources for a night concurrently.
+///
+/// At most `max_concurrency` sources are in flight at once and each one is
+/// given `source_timeout` to finish. A failing source is reported in the
+/// statuses instead of aborting the others.
+pub struct NightFetcher<'a> {
+    client: &'a RolexClient,
+    site: &'a Site,
+    max_concurrency: usize,
+    source_timeout: Duration,
+    night_plan_key: Option<String>,
+}
+
+impl<'a> NightFetcher<'a> {
+    pub fn new(client: &'a RolexClient, site: &'a Site) -> NightFetcher<'a> {
+        NightFetcher {
+            client,
+            site,
+            max_concurrency: 4,
+            source_timeout: Duration::from_secs(300),
+            night_plan_key: None,
+        }
+    }
+
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> NightFetcher<'a> {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    pub fn with_source_timeout(mut self, source_timeout: Duration) -> NightFetcher<'a> {
+        self.source_timeout = source_timeout;
+        self
+    }
+
+    pub fn with_night_plan(mut self, test_cycle_key: &str) -> NightFetcher<'a> {
+        self.night_plan_key = Some(test_cycle_key.to_owned());
+        self
+    }
+
+    pub async fn fetch(&self, date_start: &NaiveDateTime, date_end: &NaiveDateTime) -> NightData {
+        let semaphore = Semaphore::new(self.max_concurrency);
+        let params = Some(HashMap::from([
+            (
+                "min_date_added".to_owned(),
+                date_start.format(DATE_FORMAT).to_string(),
+            ),
+            (
+                "max_date_added".to_owned(),
+                date_end.format(DATE_FORMAT).to_string(),
+            ),
+        ]));
+
+        let (narrative_logs, exposure_logs, fault_logs, block_logs, night_plan) = tokio::join!(
+            self.run(
+                &semaphore,
+                Source::NarrativeLog,
+                NarrativeLog::retrieve(self.client, self.site, &params),
+            ),
+            self.run(
+                &semaphore,
+                Source::ExposureLog,
+                ExposureLog::retrieve(self.client, self.site, &params),
+            ),
+            self.run(
+                &semaphore,
+                Source::FaultLog,
+                FaultLog::retrieve(self.client, self.site, date_start, date_end),
+            ),
+            self.run(
+                &semaphore,
+                Source::BlockLog,
+                BlockLog::retrieve(self.client, self.site, date_start, date_end),
+            ),
+            self.run_night_plan(&semaphore),
+        );
+
+        let mut statuses = vec![
+            narrative_logs.0,
+            exposure_logs.0,
+            fault_logs.0,
+            block_logs.0,
+        ];
+        let night_plan = night_plan.and_then(|(status, night_plan)| {
+            statuses.push(status);
+            night_plan
+        });
+
+        NightData {
+            narrative_logs: narrative_logs.1.unwrap_or_default(),
+            exposure_logs: exposure_logs.1.unwrap_or_default(),
+            fault_logs: fault_logs.1.unwrap_or_default(),
+            block_logs: block_logs.1.unwrap_or_default(),
+            night_plan,
+            jira_issues: HashMap::new(),
+            statuses,
+        }
+    }
+
+    async fn run_night_plan(
+        &self,
+        semaphore: &Semaphore,
+    ) -> Option<(SourceStatus, Option<NightPlan>)> {
+        let test_cycle_key = self.night_plan_key.as_ref()?;
+        let (status, night_plan) = self
+            .run(semaphore, Source::NightPlan, async {
+                let night_plan =
+                    NightPlan::retrieve(self.client, self.site, test_cycle_key).await?;
+                Ok(vec![night_plan])
+            })
+            .await;
+
+        Some((status, night_plan.and_then(|mut plans| plans.pop())))
+    }
+
+    /// Run `future` once a permit is available, timing it and turning its
+    /// result into a [`SourceStatus`].
+    async fn run<T, F>(
+        &self,
+        semaphore: &Semaphore,
+        source: Source,
+        future: F,
+    ) -> (SourceStatus, Option<Vec<T>>)
+    where
+        F: Future<Output = Result<Vec<T>, Box<dyn Error>>>,
+    {
+        let _permit = semaphore.acquire().await;
+        let start = Instant::now();
+
+        let (outcome, entries) = match timeout(self.source_timeout, future).await {
+            Ok(Ok(entries)) if entries.is_empty() => (SourceOutcome::Empty, Some(entries)),
+            Ok(Ok(entries)) => (SourceOutcome::Ok(entries.len()), Some(entries)),
+            Ok(Err(error)) => (SourceOutcome::Failed(error.to_string()), None),
+            Err(_) => (SourceOutcome::TimedOut, None),
+        };
+
+        (
+            SourceStatus {
+                source,
+                outcome,
+                duration: start.elapsed(),
+            },
+            entries,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_outcomes() {
+        let client = RolexClient::new();
+        let site = Site::summit();
+        let fetcher =
+            NightFetcher::new(&client, &site).with_source_timeout(Duration::from_millis(50));
+        let semaphore = Semaphore::new(1);
+
+        let (status, entries) = fetcher
+            .run(&semaphore, Source::FaultLog, async { Ok(vec![1, 2]) })
+            .await;
+        assert_eq!(status.get_outcome(), &SourceOutcome::Ok(2));
+        assert_eq!(entries, Some(vec![1, 2]));
+
+        let (status, _) = fetcher
+            .run(&semaphore, Source::BlockLog, async {
+                Ok(Vec::<usize>::new())
+            })
+            .await;
+        assert_eq!(status.get_outcome(), &SourceOutcome::Empty);
+
+        let (status, entries) = fetcher
+            .run(&semaphore, Source::NarrativeLog, async {
+                Err::<Vec<usize>, Box<dyn Error>>("service down".into())
+            })
+            .await;
+        assert_eq!(
+            status.get_outcome(),
+            &SourceOutcome::Failed("service down".to_owned())
+        );
+        assert!(!status.is_ok());
+        assert_eq!(entries, None);
+
+        let (status, _) = fetcher
+            .run(&semaphore, Source::ExposureLog, async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                Ok(vec![1])
+            })
+            .await;
+        assert_eq!(status.get_outcome(), &SourceOutcome::TimedOut);
+    }
+}
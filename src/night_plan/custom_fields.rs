@@ -0,0 +1,332 @@
+use serde::{Deserialize, Deserializer, Serializer};
+use serde_json::Value;
+use std::{collections::HashMap, fmt};
+
+pub const TMA_ELEVATION_POSITION: &str = "End of Night - TMA El position";
+pub const TMA_AZIMUTH_POSITION: &str = "End of Night - TMA Az Position";
+pub const TMA_WALK_AROUND_PERFORMED_BY: &str = "TMA walk around - performed by";
+pub const TMA_WALK_AROUND_COMMENTS: &str = "TMA walk around - comments";
+pub const TMA_WALK_AROUND_DONE: &str = "TMA walk around done";
+pub const TMA_READY: &str = "TMA ready for use?";
+pub const END_OF_NIGHT_POWER_SUPPLY: &str = "End of Night - Power Supply Status";
+pub const END_OF_NIGHT_OSS: &str = "End of Night - OSS Power Status";
+
+/// Allowed TMA azimuth range, in degrees.
+pub const TMA_AZIMUTH_RANGE: (f64, f64) = (-280.0, 280.0);
+/// Allowed TMA elevation range, in degrees.
+pub const TMA_ELEVATION_RANGE: (f64, f64) = (0.0, 90.0);
+
+/// Power status reported at the end of the night.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PowerStatus {
+    On,
+    Off,
+    Other(String),
+}
+
+impl PowerStatus {
+    /// Parse a status as typed by operators, `None` when blank.
+    pub fn parse(value: &str) -> Option<PowerStatus> {
+        let value = value.trim();
+        match value.to_lowercase().as_str() {
+            "" => None,
+            "on" | "power on" | "powered on" => Some(PowerStatus::On),
+            "off" | "power off" | "powered off" => Some(PowerStatus::Off),
+            _ => Some(PowerStatus::Other(value.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for PowerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowerStatus::On => write!(f, "ON"),
+            PowerStatus::Off => write!(f, "OFF"),
+            PowerStatus::Other(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Parse an angle in degrees, accepting a trailing unit such as `90 deg`.
+pub fn parse_angle(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .trim_end_matches("degrees")
+        .trim_end_matches("deg")
+        .trim_end_matches('°')
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|angle| angle.is_finite())
+}
+
+fn deserialize_angle<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Number(number)) => number.as_f64(),
+        Some(Value::String(value)) => parse_angle(&value),
+        _ => None,
+    })
+}
+
+fn serialize_angle<S>(angle: &Option<f64>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match angle {
+        Some(angle) => serializer.serialize_str(&angle.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.filter(|value| !value.trim().is_empty()))
+}
+
+fn deserialize_power_status<'de, D>(deserializer: D) -> Result<Option<PowerStatus>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.and_then(|value| PowerStatus::parse(&value)))
+}
+
+fn serialize_power_status<S>(status: &Option<PowerStatus>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match status {
+        Some(status) => serializer.serialize_str(&status.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Problem found while checking the end-of-night checklist.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ChecklistFinding {
+    field: &'static str,
+    message: String,
+}
+
+impl ChecklistFinding {
+    fn new(field: &'static str, message: &str) -> ChecklistFinding {
+        ChecklistFinding {
+            field,
+            message: message.to_owned(),
+        }
+    }
+
+    pub fn get_field(&self) -> &str {
+        self.field
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ChecklistFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// End-of-night custom fields of a night plan test cycle.
+///
+/// Every field is optional, so cycles created before a field existed
+/// still deserialize, and fields not modelled here are kept in `other`.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq)]
+pub struct CustomFields {
+    #[serde(
+        rename = "End of Night - TMA El position",
+        default,
+        deserialize_with = "deserialize_angle",
+        serialize_with = "serialize_angle"
+    )]
+    tma_elevation_position: Option<f64>,
+    #[serde(
+        rename = "End of Night - TMA Az Position",
+        default,
+        deserialize_with = "deserialize_angle",
+        serialize_with = "serialize_angle"
+    )]
+    tma_azimuth_position: Option<f64>,
+    #[serde(
+        rename = "TMA walk around - performed by",
+        default,
+        deserialize_with = "deserialize_text"
+    )]
+    tma_walk_around_performed_by: Option<String>,
+    #[serde(
+        rename = "TMA walk around - comments",
+        default,
+        deserialize_with = "deserialize_text"
+    )]
+    tma_walk_around_comments: Option<String>,
+    #[serde(rename = "TMA walk around done", default)]
+    tma_walk_around_done: Option<bool>,
+    #[serde(rename = "TMA ready for use?", default)]
+    tma_ready: Option<bool>,
+    #[serde(
+        rename = "End of Night - Power Supply Status",
+        default,
+        deserialize_with = "deserialize_power_status",
+        serialize_with = "serialize_power_status"
+    )]
+    end_of_night_power_supply: Option<PowerStatus>,
+    #[serde(
+        rename = "End of Night - OSS Power Status",
+        default,
+        deserialize_with = "deserialize_power_status",
+        serialize_with = "serialize_power_status"
+    )]
+    end_of_night_oss: Option<PowerStatus>,
+    #[serde(flatten)]
+    other: HashMap<String, Value>,
+}
+
+impl CustomFields {
+    pub fn get_tma_elevation_position(&self) -> Option<f64> {
+        self.tma_elevation_position
+    }
+
+    pub fn get_tma_azimuth_position(&self) -> Option<f64> {
+        self.tma_azimuth_position
+    }
+
+    pub fn get_tma_walk_around_performed_by(&self) -> &Option<String> {
+        &self.tma_walk_around_performed_by
+    }
+
+    pub fn get_tma_walk_around_comments(&self) -> &Option<String> {
+        &self.tma_walk_around_comments
+    }
+
+    pub fn is_tma_walk_around_done(&self) -> bool {
+        self.tma_walk_around_done.unwrap_or(false)
+    }
+
+    pub fn is_tma_ready(&self) -> bool {
+        self.tma_ready.unwrap_or(false)
+    }
+
+    pub fn get_end_of_night_power_supply(&self) -> &Option<PowerStatus> {
+        &self.end_of_night_power_supply
+    }
+
+    pub fn get_end_of_night_oss(&self) -> &Option<PowerStatus> {
+        &self.end_of_night_oss
+    }
+
+    /// Custom fields not modelled above, by name.
+    pub fn get_other(&self) -> &HashMap<String, Value> {
+        &self.other
+    }
+
+    /// Check the end-of-night checklist for missing or inconsistent
+    /// entries. An empty list means the checklist is complete.
+    pub fn validate(&self) -> Vec<ChecklistFinding> {
+        let mut findings = Vec::new();
+
+        let angles = [
+            (
+                TMA_AZIMUTH_POSITION,
+                self.tma_azimuth_position,
+                TMA_AZIMUTH_RANGE,
+            ),
+            (
+                TMA_ELEVATION_POSITION,
+                self.tma_elevation_position,
+                TMA_ELEVATION_RANGE,
+            ),
+        ];
+        for (field, angle, (min, max)) in angles {
+            match angle {
+                None => findings.push(ChecklistFinding::new(
+                    field,
+                    "Missing or not an angle in degrees.",
+                )),
+                Some(angle) if !(min..=max).contains(&angle) => findings.push(
+                    ChecklistFinding::new(field, &format!("{angle} is outside [{min}, {max}].")),
+                ),
+                Some(_) => (),
+            }
+        }
+
+        if self.is_tma_ready() && !self.is_tma_walk_around_done() {
+            findings.push(ChecklistFinding::new(
+                TMA_READY,
+                "TMA marked ready for use but no walk around was done.",
+            ));
+        }
+        if self.is_tma_walk_around_done() && self.tma_walk_around_performed_by.is_none() {
+            findings.push(ChecklistFinding::new(
+                TMA_WALK_AROUND_PERFORMED_BY,
+                "Walk around done but nobody is recorded as performing it.",
+            ));
+        }
+
+        let statuses = [
+            (END_OF_NIGHT_POWER_SUPPLY, &self.end_of_night_power_supply),
+            (END_OF_NIGHT_OSS, &self.end_of_night_oss),
+        ];
+        for (field, status) in statuses {
+            match status {
+                None => findings.push(ChecklistFinding::new(field, "Missing.")),
+                Some(PowerStatus::Other(value)) => findings.push(ChecklistFinding::new(
+                    field,
+                    &format!("Unrecognized status {value:?}."),
+                )),
+                Some(_) => (),
+            }
+        }
+
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let custom_fields_json = r#"{"End of Night - TMA El position":"90.0 deg","End of Night - TMA Az Position":180,"TMA walk around done":true,"TMA ready for use?":true,"End of Night - Power Supply Status":"Powered Off","Night Summary":"Clear night."}"#;
+
+        let custom_fields: CustomFields = serde_json::from_str(custom_fields_json).unwrap();
+
+        assert_eq!(custom_fields.get_tma_elevation_position(), Some(90.0));
+        assert_eq!(custom_fields.get_tma_azimuth_position(), Some(180.0));
+        assert_eq!(custom_fields.get_tma_walk_around_performed_by(), &None);
+        assert_eq!(
+            custom_fields.get_end_of_night_power_supply(),
+            &Some(PowerStatus::Off)
+        );
+        assert_eq!(custom_fields.get_end_of_night_oss(), &None);
+        assert_eq!(custom_fields.get_other()["Night Summary"], "Clear night.");
+    }
+
+    #[test]
+    fn test_validate() {
+        let custom_fields_json = r#"{"End of Night - TMA El position":"95","End of Night - TMA Az Position":"","TMA walk around done":false,"TMA ready for use?":true,"End of Night - Power Supply Status":"ON","End of Night - OSS Power Status":"flickering"}"#;
+
+        let custom_fields: CustomFields = serde_json::from_str(custom_fields_json).unwrap();
+        let findings = custom_fields.validate();
+        let fields: Vec<&str> = findings.iter().map(|finding| finding.get_field()).collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                TMA_AZIMUTH_POSITION,
+                TMA_ELEVATION_POSITION,
+                TMA_READY,
+                END_OF_NIGHT_OSS
+            ]
+        );
+    }
+}
@@ -1 +1,7 @@
+pub mod custom_fields;
 pub mod night_plan;
+pub mod night_plan_search;
+pub mod night_plan_update;
+pub mod test_execution;
+pub mod tma_position;
+pub mod zephyr;
@@ -1,7 +1,19 @@
-use std::{env, error::Error};
+use serde_json::Value;
+use std::error::Error;
+use thiserror::Error;
 
 use url::Url;
 
+use super::{
+    custom_fields::CustomFields,
+    zephyr::{self, Reference, ZephyrCache},
+};
+use crate::{client::client::RolexClient, site::site::Site};
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorRetrievingNightPlan(pub(crate) String);
+
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Project {
     id: usize,
@@ -24,17 +36,115 @@ pub struct Owner {
     url: String,
 }
 
+/// Test cycle status as defined in the Zephyr Scale project.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq)]
+pub struct TestCycleStatus {
+    id: usize,
+    name: String,
+    description: Option<String>,
+    color: Option<String>,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    default: bool,
+}
+
+impl TestCycleStatus {
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_description(&self) -> &Option<String> {
+        &self.description
+    }
+
+    pub fn get_color(&self) -> &Option<String> {
+        &self.color
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.default
+    }
+}
+
+/// Jira Cloud user owning a test cycle.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq)]
+pub struct JiraUser {
+    #[serde(rename = "accountId")]
+    account_id: String,
+    #[serde(rename = "displayName")]
+    display_name: String,
+    #[serde(rename = "emailAddress")]
+    email_address: Option<String>,
+    #[serde(default)]
+    active: bool,
+    #[serde(rename = "timeZone")]
+    time_zone: Option<String>,
+}
+
+impl JiraUser {
+    pub fn get_account_id(&self) -> &str {
+        &self.account_id
+    }
+
+    pub fn get_display_name(&self) -> &str {
+        &self.display_name
+    }
+
+    pub fn get_email_address(&self) -> &Option<String> {
+        &self.email_address
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn get_time_zone(&self) -> &Option<String> {
+        &self.time_zone
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Links {
     #[serde(rename = "self")]
     url: String,
-    issues: Vec<String>,
+    issues: Vec<IssueLink>,
     #[serde(rename = "webLinks")]
-    web_links: Vec<String>,
+    web_links: Vec<Value>,
     #[serde(rename = "testPlans")]
     test_plans: Vec<TestPlan>,
 }
 
+/// Link from a test cycle to a Jira issue, known by its numeric id.
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct IssueLink {
+    id: usize,
+    #[serde(rename = "self")]
+    url: String,
+    #[serde(rename = "issueId")]
+    issue_id: usize,
+    target: String,
+}
+
+impl IssueLink {
+    pub fn get_issue_id(&self) -> usize {
+        self.issue_id
+    }
+
+    /// Link to the issue in the Jira REST API.
+    pub fn get_target(&self) -> &str {
+        &self.target
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct TestPlan {
     id: usize,
@@ -47,26 +157,6 @@ pub struct TestPlan {
     target: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
-pub struct CustomFields {
-    #[serde(rename = "End of Night - TMA El position")]
-    tma_elevation_position: String,
-    #[serde(rename = "End of Night - TMA Az Position")]
-    tma_azimuth_position: String,
-    #[serde(rename = "TMA walk around - performed by")]
-    tma_walk_around_performed_by: String,
-    #[serde(rename = "TMA walk around - comments")]
-    tma_walk_around_comments: String,
-    #[serde(rename = "TMA walk around done")]
-    tma_walk_around_done: bool,
-    #[serde(rename = "TMA ready for use?")]
-    tma_ready: bool,
-    #[serde(rename = "End of Night - Power Supply Status")]
-    end_of_night_power_supply: String,
-    #[serde(rename = "End of Night - OSS Power Status")]
-    end_of_night_oss: String,
-}
-
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct NightPlan {
     id: usize,
@@ -74,95 +164,149 @@ pub struct NightPlan {
     name: String,
     project: Project,
     #[serde(rename = "jiraProjectVersion")]
-    jira_project_version: Option<String>,
+    jira_project_version: Option<Reference>,
     status: Status,
-    folder: Option<String>,
+    folder: Option<Reference>,
     description: Option<String>,
     #[serde(rename = "plannedStartDate")]
-    planned_start_date: String,
+    planned_start_date: Option<String>,
     #[serde(rename = "plannedEndDate")]
-    planned_end_date: String,
+    planned_end_date: Option<String>,
     owner: Owner,
-    #[serde(rename = "customFields")]
+    #[serde(rename = "customFields", default)]
     custom_fields: CustomFields,
     links: Links,
 }
 
-// const BASE_URL: &str = "https://api.zephyrscale.smartbear.com/v2/";
-
 impl Status {}
 
 impl NightPlan {
     pub async fn retrieve(
-        base_url: &str,
+        client: &RolexClient,
+        site: &Site,
         test_cycle_key: &str,
     ) -> Result<NightPlan, Box<dyn Error>> {
-        let token = env::var("ZEPHYR_API_TOKEN")?;
-        let client = reqwest::Client::new();
+        let authorization = client.get_zephyr_authorization().await?;
         let endpoint = format!("testcycles/{test_cycle_key}");
 
-        let url = Url::parse(base_url)?.join(&endpoint)?;
+        let url = Url::parse(site.get_zephyr_url())?.join(&endpoint)?;
 
-        let response = client
-            .get(url)
-            .header("Authorization", format!("Bearer {token}"))
-            .header("Content-Type", "application/json")
-            .send()
-            .await?;
-
-        let response_text = response.text().await?;
+        client
+            .send_json(client.get_http().get(url), Some(authorization), None)
+            .await
+    }
 
-        println!("{response_text}");
-        let night_plan = serde_json::from_str(&response_text)?;
+    pub fn get_key(&self) -> &str {
+        &self.key
+    }
 
-        Ok(night_plan)
+    pub fn get_name(&self) -> &str {
+        &self.name
     }
 
-    pub async fn get_status(&self) -> Result<String, Box<dyn Error>> {
-        let token = env::var("ZEPHYR_API_TOKEN")?;
-        let client = reqwest::Client::new();
+    pub fn get_folder(&self) -> &Option<Reference> {
+        &self.folder
+    }
 
-        let response = client
-            .get(&self.status.url)
-            .header("Authorization", format!("Bearer {token}"))
-            .header("Content-Type", "application/json")
-            .send()
-            .await?;
+    pub fn get_planned_start_date(&self) -> &Option<String> {
+        &self.planned_start_date
+    }
 
-        let response_text = response.text().await?;
+    pub fn get_planned_end_date(&self) -> &Option<String> {
+        &self.planned_end_date
+    }
 
-        Ok(response_text)
+    pub fn get_custom_fields(&self) -> &CustomFields {
+        &self.custom_fields
     }
 
-    pub async fn get_owner(&self) -> Result<String, Box<dyn Error>> {
-        let token = env::var("JIRA_CLOUD_API_TOKEN")?;
-        let client = reqwest::Client::new();
+    /// Link to the test cycle in the Zephyr Scale pages of Jira.
+    pub fn get_web_url(&self, site: &Site) -> String {
+        let project = self.key.split('-').next().unwrap_or_default();
+        format!(
+            "{}projects/{project}?selectedItem=com.atlassian.plugins.atlassian-connect-plugin:com.kanoah.test-manager__main-project-page#!/v2/testCycle/{}",
+            site.get_jira_url(),
+            self.key
+        )
+    }
 
-        let response = client
-            .get(&self.owner.url)
-            .header("Authorization", format!("Basic {token}"))
-            .header("Content-Type", "application/json")
-            .send()
-            .await?;
+    /// Ids of the Jira issues linked to this test cycle.
+    pub fn get_issue_ids(&self) -> Vec<usize> {
+        self.links
+            .issues
+            .iter()
+            .map(|issue_link| issue_link.issue_id)
+            .collect()
+    }
 
-        let response_text = response.text().await?;
+    /// Resolve the cycle status, reusing `cache` when the same status was
+    /// already looked up.
+    pub async fn get_status(
+        &self,
+        client: &RolexClient,
+        cache: &ZephyrCache,
+    ) -> Result<TestCycleStatus, Box<dyn Error>> {
+        zephyr::resolve_status(client, cache, self.status.id, &self.status.url).await
+    }
 
-        Ok(response_text)
+    /// Resolve the cycle owner, reusing `cache` when the same user was
+    /// already looked up.
+    pub async fn get_owner(
+        &self,
+        client: &RolexClient,
+        cache: &ZephyrCache,
+    ) -> Result<JiraUser, Box<dyn Error>> {
+        zephyr::resolve_jira_user(client, cache, &self.owner.id, &self.owner.url).await
     }
 
-    pub async fn get_links(&self) -> Result<String, Box<dyn Error>> {
-        let token = env::var("ZEPHYR_API_TOKEN")?;
-        let client = reqwest::Client::new();
+    /// Fetch the current Jira issue links of the test cycle.
+    pub async fn get_links(&self, client: &RolexClient) -> Result<Vec<IssueLink>, Box<dyn Error>> {
+        let authorization = client.get_zephyr_authorization().await?;
 
-        let response = client
-            .get(&self.links.url)
-            .header("Authorization", format!("Bearer {token}"))
-            .header("Content-Type", "application/json")
-            .send()
+        let links: Links = client
+            .send_json(
+                client.get_http().get(&self.links.url),
+                Some(authorization),
+                None,
+            )
             .await?;
 
-        let response_text = response.text().await?;
+        Ok(links.issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize_status_and_owner() {
+        let status_json = r##"{"id":4527563,"project":{"id":10251,"self":"https://api.zephyrscale.smartbear.com/v2/projects/10251"},"name":"Done","description":"Night completed.","index":2,"color":"#2fa84f","archived":false,"default":false}"##;
+        let owner_json = r#"{"self":"https://rubinobs.atlassian.net/rest/api/2/user?accountId=5fb2f8a4e1","accountId":"5fb2f8a4e1","accountType":"atlassian","emailAddress":"observer@lsst.org","displayName":"Night Observer","active":true,"timeZone":"America/Santiago","locale":"en_US"}"#;
+
+        let status: TestCycleStatus = serde_json::from_str(status_json).unwrap();
+        let owner: JiraUser = serde_json::from_str(owner_json).unwrap();
+
+        assert_eq!(status.get_id(), 4527563);
+        assert_eq!(status.get_name(), "Done");
+        assert_eq!(status.get_color(), &Some("#2fa84f".to_owned()));
+        assert!(!status.is_archived());
+        assert_eq!(owner.get_account_id(), "5fb2f8a4e1");
+        assert_eq!(owner.get_display_name(), "Night Observer");
+        assert_eq!(owner.get_time_zone(), &Some("America/Santiago".to_owned()));
+        assert!(owner.is_active());
+    }
+
+    #[test]
+    fn test_issue_links() {
+        let links_json = r#"{"self":"https://api.zephyrscale.smartbear.com/v2/testcycles/1882/links","issues":[{"self":"https://api.zephyrscale.smartbear.com/v2/links/51427","issueId":10734,"id":51427,"target":"https://rubinobs.atlassian.net/rest/api/latest/issue/10734","type":"COVERAGE"}],"webLinks":[],"testPlans":[]}"#;
+
+        let night_plan = NightPlan {
+            links: serde_json::from_str(links_json).unwrap(),
+            ..NightPlan::default()
+        };
 
-        Ok(response_text)
+        assert_eq!(night_plan.get_issue_ids(), vec![10734]);
     }
 }
@@ -0,0 +1,187 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use std::error::Error;
+use url::Url;
+
+use super::{
+    night_plan::{ErrorRetrievingNightPlan, NightPlan},
+    zephyr::{self, ZephyrCache},
+};
+use crate::{client::client::RolexClient, site::site::Site};
+
+/// Filters for listing the test cycles of a Zephyr Scale project.
+///
+/// Zephyr Scale only filters by project and folder on the server, the
+/// planned date range and status are checked on the returned cycles.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NightPlanSearch {
+    project_key: String,
+    folder_id: Option<usize>,
+    planned_after: Option<NaiveDateTime>,
+    planned_before: Option<NaiveDateTime>,
+    status_name: Option<String>,
+}
+
+impl NightPlanSearch {
+    pub fn new(project_key: &str) -> NightPlanSearch {
+        NightPlanSearch {
+            project_key: project_key.to_owned(),
+            ..NightPlanSearch::default()
+        }
+    }
+
+    pub fn with_folder_id(mut self, folder_id: usize) -> NightPlanSearch {
+        self.folder_id = Some(folder_id);
+        self
+    }
+
+    /// Only keep cycles whose planned dates overlap `[after, before)`.
+    pub fn with_planned_range(
+        mut self,
+        after: &NaiveDateTime,
+        before: &NaiveDateTime,
+    ) -> NightPlanSearch {
+        self.planned_after = Some(*after);
+        self.planned_before = Some(*before);
+        self
+    }
+
+    /// Only keep cycles planned for the observing night `day_obs`
+    /// (`YYYYMMDD`), which runs from noon UTC on that day to noon UTC on
+    /// the next one.
+    pub fn with_day_obs(self, day_obs: usize) -> Option<NightPlanSearch> {
+        let date = NaiveDate::from_ymd_opt(
+            (day_obs / 10000) as i32,
+            ((day_obs / 100) % 100) as u32,
+            (day_obs % 100) as u32,
+        )?;
+        let night_start = date.and_hms_opt(12, 0, 0)?;
+
+        Some(self.with_planned_range(&night_start, &(night_start + Duration::days(1))))
+    }
+
+    pub fn with_status_name(mut self, status_name: &str) -> NightPlanSearch {
+        self.status_name = Some(status_name.to_owned());
+        self
+    }
+
+    /// Whether the planned dates of `night_plan` overlap the search range.
+    pub fn is_in_planned_range(&self, night_plan: &NightPlan) -> bool {
+        let parse = |date: &Option<String>| {
+            DateTime::parse_from_rfc3339(date.as_deref()?)
+                .ok()
+                .map(|date| date.with_timezone(&Utc).naive_utc())
+        };
+        let (Some(planned_start), Some(planned_end)) = (
+            parse(night_plan.get_planned_start_date()),
+            parse(night_plan.get_planned_end_date()),
+        ) else {
+            return false;
+        };
+
+        self.planned_before
+            .is_none_or(|before| planned_start < before)
+            && self.planned_after.is_none_or(|after| planned_end > after)
+    }
+
+    /// List the matching test cycles, walking every page of the listing.
+    pub async fn search(
+        &self,
+        client: &RolexClient,
+        site: &Site,
+        cache: &ZephyrCache,
+    ) -> Result<Vec<NightPlan>, Box<dyn Error>> {
+        let mut url = Url::parse(site.get_zephyr_url())?.join("testcycles")?;
+        url.query_pairs_mut()
+            .append_pair("projectKey", &self.project_key);
+        if let Some(folder_id) = self.folder_id {
+            url.query_pairs_mut()
+                .append_pair("folderId", &folder_id.to_string());
+        }
+
+        let night_plans: Vec<NightPlan> = zephyr::get_all_pages(client, url).await?;
+
+        let mut matches = Vec::new();
+        for night_plan in night_plans {
+            if !self.is_in_planned_range(&night_plan) {
+                continue;
+            }
+            if let Some(status_name) = &self.status_name {
+                let status = night_plan.get_status(client, cache).await?;
+                if !status.get_name().eq_ignore_ascii_case(status_name) {
+                    continue;
+                }
+            }
+            matches.push(night_plan);
+        }
+        Ok(matches)
+    }
+}
+
+impl NightPlan {
+    /// Find the night plans of `project_key` covering `day_obs`, failing
+    /// when `day_obs` is not a valid `YYYYMMDD` date.
+    pub async fn find_for_day_obs(
+        client: &RolexClient,
+        site: &Site,
+        cache: &ZephyrCache,
+        project_key: &str,
+        day_obs: usize,
+    ) -> Result<Vec<NightPlan>, Box<dyn Error>> {
+        match NightPlanSearch::new(project_key).with_day_obs(day_obs) {
+            Some(search) => search.search(client, site, cache).await,
+            None => Err(Box::new(ErrorRetrievingNightPlan(format!(
+                "Invalid day_obs {day_obs}"
+            )))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_is_in_planned_range() {
+        let night_plan_json = r#"{"id":1882,"key":"BLOCK-R19","name":"2024-08-13","project":{"id":10251,"self":"https://api.zephyrscale.smartbear.com/v2/projects/10251"},"jiraProjectVersion":null,"status":{"id":4527562,"self":"https://api.zephyrscale.smartbear.com/v2/statuses/4527562"},"folder":null,"description":null,"plannedStartDate":"2024-08-13T22:00:00Z","plannedEndDate":"2024-08-14T10:00:00Z","owner":{"self":"https://rubinobs.atlassian.net/rest/api/2/user?accountId=5fb2f8a4e1","accountId":"5fb2f8a4e1"},"customFields":{"End of Night - TMA El position":"","End of Night - TMA Az Position":"","TMA walk around - performed by":"","TMA walk around - comments":"","TMA walk around done":false,"TMA ready for use?":false,"End of Night - Power Supply Status":"","End of Night - OSS Power Status":""},"links":{"self":"https://api.zephyrscale.smartbear.com/v2/testcycles/1882/links","issues":[],"webLinks":[],"testPlans":[]}}"#;
+        let night_plan: NightPlan = serde_json::from_str(night_plan_json).unwrap();
+
+        let search = NightPlanSearch::new("BLOCK");
+        assert!(search
+            .clone()
+            .with_day_obs(20240813)
+            .unwrap()
+            .is_in_planned_range(&night_plan));
+        assert!(!search
+            .clone()
+            .with_day_obs(20240814)
+            .unwrap()
+            .is_in_planned_range(&night_plan));
+        assert!(!search
+            .clone()
+            .with_day_obs(20240812)
+            .unwrap()
+            .is_in_planned_range(&night_plan));
+        assert!(search.clone().with_day_obs(20241332).is_none());
+        assert!(search.is_in_planned_range(&night_plan));
+    }
+
+    #[test]
+    fn test_deserialize_search_results() {
+        let values_json = r#"[{"id":1901,"key":"BLOCK-R23","name":"2024-08-14","project":{"id":10251,"self":"https://api.zephyrscale.smartbear.com/v2/projects/10251"},"jiraProjectVersion":{"id":10012,"self":"https://rubinobs.atlassian.net/rest/api/2/version/10012"},"status":{"id":4527562,"self":"https://api.zephyrscale.smartbear.com/v2/statuses/4527562"},"folder":{"id":18374,"self":"https://api.zephyrscale.smartbear.com/v2/folders/18374"},"description":null,"plannedStartDate":"2024-08-14T22:00:00Z","plannedEndDate":"2024-08-15T10:00:00Z","owner":{"self":"https://rubinobs.atlassian.net/rest/api/2/user?accountId=5fb2f8a4e1","accountId":"5fb2f8a4e1"},"customFields":{},"links":{"self":"https://api.zephyrscale.smartbear.com/v2/testcycles/1901/links","issues":[],"webLinks":[],"testPlans":[]}},{"id":1902,"key":"BLOCK-R24","name":"Undated","project":{"id":10251,"self":"https://api.zephyrscale.smartbear.com/v2/projects/10251"},"jiraProjectVersion":null,"status":{"id":4527562,"self":"https://api.zephyrscale.smartbear.com/v2/statuses/4527562"},"folder":{"id":18374,"self":"https://api.zephyrscale.smartbear.com/v2/folders/18374"},"description":null,"plannedStartDate":null,"plannedEndDate":null,"owner":{"self":"https://rubinobs.atlassian.net/rest/api/2/user?accountId=5fb2f8a4e1","accountId":"5fb2f8a4e1"},"customFields":{},"links":{"self":"https://api.zephyrscale.smartbear.com/v2/testcycles/1902/links","issues":[],"webLinks":[],"testPlans":[]}}]"#;
+        let night_plans: Vec<NightPlan> = serde_json::from_str(values_json).unwrap();
+
+        let search = NightPlanSearch::new("BLOCK")
+            .with_folder_id(18374)
+            .with_day_obs(20240814)
+            .unwrap();
+
+        assert_eq!(
+            night_plans[0].get_folder().as_ref().unwrap().get_id(),
+            18374
+        );
+        assert!(search.is_in_planned_range(&night_plans[0]));
+        assert_eq!(night_plans[1].get_planned_start_date(), &None);
+        assert!(!search.is_in_planned_range(&night_plans[1]));
+    }
+}
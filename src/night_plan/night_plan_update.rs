@@ -0,0 +1,270 @@
+use serde_json::{Map, Value};
+use std::error::Error;
+use thiserror::Error;
+use url::Url;
+
+use super::{
+    custom_fields::{
+        parse_angle, PowerStatus, END_OF_NIGHT_OSS, END_OF_NIGHT_POWER_SUPPLY,
+        TMA_AZIMUTH_POSITION, TMA_AZIMUTH_RANGE, TMA_ELEVATION_POSITION, TMA_ELEVATION_RANGE,
+        TMA_READY, TMA_WALK_AROUND_COMMENTS, TMA_WALK_AROUND_DONE, TMA_WALK_AROUND_PERFORMED_BY,
+    },
+    night_plan::NightPlan,
+};
+use crate::{client::client::RolexClient, site::site::Site};
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorUpdatingNightPlan(String);
+
+/// Changes to apply to the end-of-night fields of a test cycle.
+///
+/// Only the fields that were set are written, everything else on the
+/// cycle is sent back exactly as it was read.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct NightPlanUpdate {
+    tma_elevation_position: Option<String>,
+    tma_azimuth_position: Option<String>,
+    tma_walk_around_performed_by: Option<String>,
+    tma_walk_around_comments: Option<String>,
+    tma_walk_around_done: Option<bool>,
+    tma_ready: Option<bool>,
+    end_of_night_power_supply: Option<String>,
+    end_of_night_oss: Option<String>,
+    status_id: Option<usize>,
+}
+
+impl NightPlanUpdate {
+    pub fn new() -> NightPlanUpdate {
+        NightPlanUpdate::default()
+    }
+
+    pub fn with_tma_position(mut self, azimuth: &str, elevation: &str) -> NightPlanUpdate {
+        self.tma_azimuth_position = Some(azimuth.to_owned());
+        self.tma_elevation_position = Some(elevation.to_owned());
+        self
+    }
+
+    pub fn with_tma_walk_around(
+        mut self,
+        done: bool,
+        performed_by: &str,
+        comments: &str,
+    ) -> NightPlanUpdate {
+        self.tma_walk_around_done = Some(done);
+        self.tma_walk_around_performed_by = Some(performed_by.to_owned());
+        self.tma_walk_around_comments = Some(comments.to_owned());
+        self
+    }
+
+    pub fn with_tma_ready(mut self, tma_ready: bool) -> NightPlanUpdate {
+        self.tma_ready = Some(tma_ready);
+        self
+    }
+
+    pub fn with_end_of_night_power_supply(mut self, status: &PowerStatus) -> NightPlanUpdate {
+        self.end_of_night_power_supply = Some(status.to_string());
+        self
+    }
+
+    pub fn with_end_of_night_oss(mut self, status: &PowerStatus) -> NightPlanUpdate {
+        self.end_of_night_oss = Some(status.to_string());
+        self
+    }
+
+    pub fn with_status_id(mut self, status_id: usize) -> NightPlanUpdate {
+        self.status_id = Some(status_id);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == NightPlanUpdate::default()
+    }
+
+    /// Check the update before anything is sent to Zephyr Scale.
+    pub fn validate(&self) -> Result<(), ErrorUpdatingNightPlan> {
+        if self.is_empty() {
+            return Err(ErrorUpdatingNightPlan("Nothing to update.".to_owned()));
+        }
+
+        let angles = [
+            (
+                TMA_AZIMUTH_POSITION,
+                &self.tma_azimuth_position,
+                TMA_AZIMUTH_RANGE,
+            ),
+            (
+                TMA_ELEVATION_POSITION,
+                &self.tma_elevation_position,
+                TMA_ELEVATION_RANGE,
+            ),
+        ];
+        for (field, value, (min, max)) in angles {
+            let Some(value) = value else {
+                continue;
+            };
+            match parse_angle(value) {
+                Some(angle) if (min..=max).contains(&angle) => (),
+                _ => {
+                    return Err(ErrorUpdatingNightPlan(format!(
+                        "{field} must be an angle between {min} and {max} degrees, got {value:?}."
+                    )))
+                }
+            }
+        }
+
+        let has_performed_by = self
+            .tma_walk_around_performed_by
+            .as_ref()
+            .is_some_and(|performed_by| !performed_by.trim().is_empty());
+        if self.tma_walk_around_done == Some(true) && !has_performed_by {
+            return Err(ErrorUpdatingNightPlan(format!(
+                "{TMA_WALK_AROUND_PERFORMED_BY} is required when the walk around is done."
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Write the fields that were set into a test cycle as returned by
+    /// `GET testcycles/{key}`.
+    pub fn apply(&self, test_cycle: &mut Value) -> Result<(), ErrorUpdatingNightPlan> {
+        let Some(test_cycle) = test_cycle.as_object_mut() else {
+            return Err(ErrorUpdatingNightPlan(
+                "Test cycle is not a JSON object.".to_owned(),
+            ));
+        };
+
+        let custom_fields = test_cycle
+            .entry("customFields")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if custom_fields.is_null() {
+            *custom_fields = Value::Object(Map::new());
+        }
+        let Some(custom_fields) = custom_fields.as_object_mut() else {
+            return Err(ErrorUpdatingNightPlan(
+                "Test cycle customFields is not a JSON object.".to_owned(),
+            ));
+        };
+
+        let strings = [
+            (TMA_ELEVATION_POSITION, &self.tma_elevation_position),
+            (TMA_AZIMUTH_POSITION, &self.tma_azimuth_position),
+            (
+                TMA_WALK_AROUND_PERFORMED_BY,
+                &self.tma_walk_around_performed_by,
+            ),
+            (TMA_WALK_AROUND_COMMENTS, &self.tma_walk_around_comments),
+            (END_OF_NIGHT_POWER_SUPPLY, &self.end_of_night_power_supply),
+            (END_OF_NIGHT_OSS, &self.end_of_night_oss),
+        ];
+        for (field, value) in strings {
+            if let Some(value) = value {
+                custom_fields.insert(field.to_owned(), Value::from(value.to_owned()));
+            }
+        }
+
+        let flags = [
+            (TMA_WALK_AROUND_DONE, self.tma_walk_around_done),
+            (TMA_READY, self.tma_ready),
+        ];
+        for (field, value) in flags {
+            if let Some(value) = value {
+                custom_fields.insert(field.to_owned(), Value::from(value));
+            }
+        }
+
+        if let Some(status_id) = self.status_id {
+            let mut status = Map::new();
+            status.insert("id".to_owned(), Value::from(status_id));
+            test_cycle.insert("status".to_owned(), Value::Object(status));
+        }
+
+        Ok(())
+    }
+}
+
+impl NightPlan {
+    /// Apply `update` to the test cycle `test_cycle_key`.
+    ///
+    /// The cycle is read, modified and written back in full, as Zephyr
+    /// Scale expects on `PUT`, then read again and returned.
+    pub async fn update(
+        client: &RolexClient,
+        site: &Site,
+        test_cycle_key: &str,
+        update: &NightPlanUpdate,
+    ) -> Result<NightPlan, Box<dyn Error>> {
+        update.validate()?;
+
+        let authorization = client.get_zephyr_authorization().await?;
+        let url =
+            Url::parse(site.get_zephyr_url())?.join(&format!("testcycles/{test_cycle_key}"))?;
+
+        let mut test_cycle: Value = client
+            .send_json(
+                client.get_http().get(url.clone()),
+                Some(authorization),
+                None,
+            )
+            .await?;
+        update.apply(&mut test_cycle)?;
+        client
+            .send_json::<Value>(
+                client.get_http().put(url),
+                Some(authorization),
+                Some(&test_cycle),
+            )
+            .await?;
+
+        NightPlan::retrieve(client, site, test_cycle_key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(NightPlanUpdate::new().validate().is_err());
+        assert!(NightPlanUpdate::new()
+            .with_tma_position("180.0", "90")
+            .validate()
+            .is_ok());
+        assert!(NightPlanUpdate::new()
+            .with_tma_position("180.0", "95")
+            .validate()
+            .is_err());
+        assert!(NightPlanUpdate::new()
+            .with_tma_position("north", "45")
+            .validate()
+            .is_err());
+        assert!(NightPlanUpdate::new()
+            .with_tma_walk_around(true, " ", "")
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut test_cycle: Value = serde_json::from_str(r#"{"id":1882,"key":"BLOCK-R19","name":"2024-08-13","status":{"id":4527562,"self":"https://api.zephyrscale.smartbear.com/v2/statuses/4527562"},"customFields":{"End of Night - TMA El position":"","TMA walk around - comments":"All good.","Some other field":3}}"#).unwrap();
+
+        NightPlanUpdate::new()
+            .with_tma_position("0.5", "90.0")
+            .with_tma_ready(true)
+            .with_status_id(4527563)
+            .apply(&mut test_cycle)
+            .unwrap();
+
+        let custom_fields = &test_cycle["customFields"];
+        assert_eq!(custom_fields[TMA_ELEVATION_POSITION], "90.0");
+        assert_eq!(custom_fields[TMA_AZIMUTH_POSITION], "0.5");
+        assert_eq!(custom_fields[TMA_READY], true);
+        assert_eq!(custom_fields[TMA_WALK_AROUND_COMMENTS], "All good.");
+        assert_eq!(custom_fields["Some other field"], 3);
+        assert_eq!(test_cycle["status"]["id"], 4527563);
+        assert_eq!(test_cycle["name"], "2024-08-13");
+    }
+}
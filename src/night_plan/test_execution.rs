@@ -0,0 +1,297 @@
+use chrono::{DateTime, Duration};
+use std::{collections::HashMap, error::Error};
+use url::Url;
+
+use super::{
+    night_plan::NightPlan,
+    zephyr::{self, Reference, ZephyrCache},
+};
+use crate::{client::client::RolexClient, site::site::Site};
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+struct TestCase {
+    key: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+struct Environment {
+    id: usize,
+    name: String,
+}
+
+/// Test execution as listed by Zephyr Scale.
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct TestExecution {
+    id: usize,
+    key: String,
+    #[serde(rename = "testCase")]
+    test_case: Reference,
+    environment: Option<Reference>,
+    #[serde(rename = "testExecutionStatus")]
+    test_execution_status: Reference,
+    #[serde(rename = "actualEndDate")]
+    actual_end_date: Option<String>,
+    /// Time spent executing, in milliseconds.
+    #[serde(rename = "executionTime")]
+    execution_time: Option<i64>,
+    #[serde(rename = "executedById")]
+    executed_by_id: Option<String>,
+    comment: Option<String>,
+}
+
+impl TestExecution {
+    /// Zephyr Scale only records when an execution ended, the start is
+    /// derived from the execution time.
+    pub fn get_actual_start_date(&self) -> Option<String> {
+        let actual_end_date = DateTime::parse_from_rfc3339(self.actual_end_date.as_ref()?).ok()?;
+        let execution_time = Duration::milliseconds(self.execution_time?);
+
+        Some((actual_end_date - execution_time).to_rfc3339())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+struct InlineStep {
+    description: Option<String>,
+    #[serde(rename = "testData")]
+    test_data: Option<String>,
+    #[serde(rename = "expectedResult")]
+    expected_result: Option<String>,
+    #[serde(rename = "actualResult")]
+    actual_result: Option<String>,
+    status: Option<Reference>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+struct TestStep {
+    inline: Option<InlineStep>,
+}
+
+/// Outcome of one step of a test execution.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq)]
+pub struct TestStepResult {
+    index: usize,
+    description: Option<String>,
+    test_data: Option<String>,
+    expected_result: Option<String>,
+    actual_result: Option<String>,
+    status: Option<String>,
+}
+
+/// Test execution of a night plan, with references resolved to names.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq)]
+pub struct TestExecutionResult {
+    key: String,
+    test_case_key: String,
+    test_case_name: String,
+    status: String,
+    executed_by: Option<String>,
+    actual_start_date: Option<String>,
+    actual_end_date: Option<String>,
+    environment: Option<String>,
+    comment: Option<String>,
+    steps: Option<Vec<TestStepResult>>,
+}
+
+impl TestExecutionResult {
+    pub fn get_key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn get_test_case_key(&self) -> &str {
+        &self.test_case_key
+    }
+
+    pub fn get_test_case_name(&self) -> &str {
+        &self.test_case_name
+    }
+
+    pub fn get_status(&self) -> &str {
+        &self.status
+    }
+
+    pub fn get_executed_by(&self) -> &Option<String> {
+        &self.executed_by
+    }
+
+    pub fn get_actual_start_date(&self) -> &Option<String> {
+        &self.actual_start_date
+    }
+
+    pub fn get_actual_end_date(&self) -> &Option<String> {
+        &self.actual_end_date
+    }
+
+    pub fn get_environment(&self) -> &Option<String> {
+        &self.environment
+    }
+
+    pub fn get_comment(&self) -> &Option<String> {
+        &self.comment
+    }
+
+    pub fn get_steps(&self) -> &Option<Vec<TestStepResult>> {
+        &self.steps
+    }
+}
+
+impl NightPlan {
+    /// List every test execution of this test cycle.
+    pub async fn get_test_executions(
+        &self,
+        client: &RolexClient,
+        site: &Site,
+    ) -> Result<Vec<TestExecution>, Box<dyn Error>> {
+        let mut url = Url::parse(site.get_zephyr_url())?.join("testexecutions")?;
+        url.query_pairs_mut()
+            .append_pair("testCycle", self.get_key());
+
+        zephyr::get_all_pages(client, url).await
+    }
+
+    /// List the test executions of this cycle with test cases, statuses,
+    /// users and environments resolved, optionally including step results.
+    pub async fn get_test_execution_results(
+        &self,
+        client: &RolexClient,
+        site: &Site,
+        cache: &ZephyrCache,
+        include_steps: bool,
+    ) -> Result<Vec<TestExecutionResult>, Box<dyn Error>> {
+        let authorization = client.get_zephyr_authorization().await?;
+
+        let mut test_cases: HashMap<String, TestCase> = HashMap::new();
+        let mut environments: HashMap<usize, String> = HashMap::new();
+        let mut results = Vec::new();
+
+        for test_execution in self.get_test_executions(client, site).await? {
+            let test_case_url = &test_execution.test_case.url;
+            if !test_cases.contains_key(test_case_url) {
+                let test_case: TestCase = client
+                    .send_json(
+                        client.get_http().get(test_case_url),
+                        Some(authorization),
+                        None,
+                    )
+                    .await?;
+                test_cases.insert(test_case_url.to_owned(), test_case);
+            }
+            let test_case = &test_cases[test_case_url];
+
+            let status = zephyr::resolve_status(
+                client,
+                cache,
+                test_execution.test_execution_status.id,
+                &test_execution.test_execution_status.url,
+            )
+            .await?;
+
+            let executed_by = match &test_execution.executed_by_id {
+                Some(account_id) => {
+                    let url = zephyr::get_jira_user_url(site, account_id)?;
+                    let user =
+                        zephyr::resolve_jira_user(client, cache, account_id, url.as_str()).await?;
+                    Some(user.get_display_name().to_owned())
+                }
+                None => None,
+            };
+
+            let environment = match &test_execution.environment {
+                Some(reference) => {
+                    if !environments.contains_key(&reference.id) {
+                        let environment: Environment = client
+                            .send_json(
+                                client.get_http().get(&reference.url),
+                                Some(authorization),
+                                None,
+                            )
+                            .await?;
+                        environments.insert(environment.id, environment.name);
+                    }
+                    environments.get(&reference.id).cloned()
+                }
+                None => None,
+            };
+
+            let steps = if include_steps {
+                Some(get_step_results(client, site, cache, &test_execution.key).await?)
+            } else {
+                None
+            };
+
+            results.push(TestExecutionResult {
+                actual_start_date: test_execution.get_actual_start_date(),
+                key: test_execution.key,
+                test_case_key: test_case.key.to_owned(),
+                test_case_name: test_case.name.to_owned(),
+                status: status.get_name().to_owned(),
+                executed_by,
+                actual_end_date: test_execution.actual_end_date,
+                environment,
+                comment: test_execution.comment,
+                steps,
+            });
+        }
+        Ok(results)
+    }
+}
+
+async fn get_step_results(
+    client: &RolexClient,
+    site: &Site,
+    cache: &ZephyrCache,
+    test_execution_key: &str,
+) -> Result<Vec<TestStepResult>, Box<dyn Error>> {
+    let url = Url::parse(site.get_zephyr_url())?
+        .join(&format!("testexecutions/{test_execution_key}/teststeps"))?;
+    let test_steps: Vec<TestStep> = zephyr::get_all_pages(client, url).await?;
+
+    let mut step_results = Vec::new();
+    for (index, test_step) in test_steps.into_iter().enumerate() {
+        let Some(inline) = test_step.inline else {
+            continue;
+        };
+        let status = match &inline.status {
+            Some(reference) => Some(
+                zephyr::resolve_status(client, cache, reference.id, &reference.url)
+                    .await?
+                    .get_name()
+                    .to_owned(),
+            ),
+            None => None,
+        };
+
+        step_results.push(TestStepResult {
+            index,
+            description: inline.description,
+            test_data: inline.test_data,
+            expected_result: inline.expected_result,
+            actual_result: inline.actual_result,
+            status,
+        });
+    }
+    Ok(step_results)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let test_execution_json = r#"{"id":91237,"key":"BLOCK-E4021","project":{"id":10251,"self":"https://api.zephyrscale.smartbear.com/v2/projects/10251"},"testCase":{"self":"https://api.zephyrscale.smartbear.com/v2/testcases/BLOCK-T17/versions/3","id":2339912},"environment":null,"jiraProjectVersion":null,"testExecutionStatus":{"id":4527560,"self":"https://api.zephyrscale.smartbear.com/v2/statuses/4527560"},"actualEndDate":"2024-08-14T03:15:00Z","estimatedTime":null,"executionTime":1800000,"executedById":"5fb2f8a4e1","assignedToId":null,"comment":"Dome closed due to humidity.","automated":false,"testCycle":{"self":"https://api.zephyrscale.smartbear.com/v2/testcycles/1882","id":1882},"customFields":{},"links":{"self":"https://api.zephyrscale.smartbear.com/v2/testexecutions/91237/links","issues":[]}}"#;
+
+        let test_execution: TestExecution = serde_json::from_str(test_execution_json).unwrap();
+
+        assert_eq!(test_execution.key, "BLOCK-E4021");
+        assert_eq!(test_execution.test_case.id, 2339912);
+        assert_eq!(test_execution.test_execution_status.id, 4527560);
+        assert!(test_execution.environment.is_none());
+        assert_eq!(
+            test_execution.get_actual_start_date(),
+            Some("2024-08-14T02:45:00+00:00".to_owned())
+        );
+    }
+}
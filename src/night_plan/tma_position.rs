@@ -0,0 +1,139 @@
+use chrono::NaiveDateTime;
+use std::error::Error;
+use thiserror::Error;
+
+use super::night_plan_update::NightPlanUpdate;
+use crate::{client::client::RolexClient, efd::efd_query::EfdQuery, site::site::Site};
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorRetrievingTmaPosition(String);
+
+type Row = (String, f64);
+
+const AZIMUTH_SELECT: &str =
+    r#"SELECT last("actualPosition") FROM "efd"."autogen"."lsst.sal.MTMount.azimuth""#;
+const ELEVATION_SELECT: &str =
+    r#"SELECT last("actualPosition") FROM "efd"."autogen"."lsst.sal.MTMount.elevation""#;
+
+/// Final TMA position of a night, as reported by MTMount.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct TmaPosition {
+    azimuth: f64,
+    azimuth_time: String,
+    elevation: f64,
+    elevation_time: String,
+}
+
+impl TmaPosition {
+    /// Query the last azimuth and elevation actual positions published
+    /// within the night window.
+    pub async fn retrieve(
+        client: &RolexClient,
+        site: &Site,
+        date_start: &NaiveDateTime,
+        date_end: &NaiveDateTime,
+    ) -> Result<TmaPosition, Box<dyn Error>> {
+        let efd_query = EfdQuery::default();
+
+        let (azimuth_time, azimuth) = TmaPosition::last_position(
+            &efd_query,
+            client,
+            site,
+            AZIMUTH_SELECT,
+            date_start,
+            date_end,
+        )
+        .await?;
+        let (elevation_time, elevation) = TmaPosition::last_position(
+            &efd_query,
+            client,
+            site,
+            ELEVATION_SELECT,
+            date_start,
+            date_end,
+        )
+        .await?;
+
+        Ok(TmaPosition {
+            azimuth,
+            azimuth_time,
+            elevation,
+            elevation_time,
+        })
+    }
+
+    async fn last_position(
+        efd_query: &EfdQuery,
+        client: &RolexClient,
+        site: &Site,
+        select: &str,
+        date_start: &NaiveDateTime,
+        date_end: &NaiveDateTime,
+    ) -> Result<Row, Box<dyn Error>> {
+        // With a chunked window there is one `last` per chunk, the final
+        // row is the last one of the night.
+        let rows: Vec<Row> = efd_query
+            .query(client, site, select, date_start, date_end, |row: &Row| {
+                &row.0
+            })
+            .await?;
+
+        rows.into_iter().last().ok_or_else(|| {
+            Box::new(ErrorRetrievingTmaPosition(format!(
+                "No MTMount data between {date_start} and {date_end}."
+            ))) as Box<dyn Error>
+        })
+    }
+
+    pub fn get_azimuth(&self) -> f64 {
+        self.azimuth
+    }
+
+    pub fn get_elevation(&self) -> f64 {
+        self.elevation
+    }
+
+    pub fn get_azimuth_time(&self) -> &str {
+        &self.azimuth_time
+    }
+
+    pub fn get_elevation_time(&self) -> &str {
+        &self.elevation_time
+    }
+
+    /// Format an angle the way it is entered in the night plan.
+    pub fn format_angle(angle: f64) -> String {
+        format!("{angle:.2}")
+    }
+
+    /// Build the night plan update filling the end-of-night TMA position.
+    pub fn to_update(&self) -> NightPlanUpdate {
+        NightPlanUpdate::new().with_tma_position(
+            &TmaPosition::format_angle(self.azimuth),
+            &TmaPosition::format_angle(self.elevation),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_to_update() {
+        let tma_position = TmaPosition {
+            azimuth: 179.998713,
+            azimuth_time: "2024-08-14T10:02:11.201Z".to_owned(),
+            elevation: 89.5,
+            elevation_time: "2024-08-14T10:02:11.201Z".to_owned(),
+        };
+
+        assert_eq!(
+            tma_position.to_update(),
+            NightPlanUpdate::new().with_tma_position("180.00", "89.50")
+        );
+        assert!(tma_position.to_update().validate().is_ok());
+    }
+}
@@ -0,0 +1,145 @@
+use serde::de::DeserializeOwned;
+use std::{collections::HashMap, error::Error};
+use tokio::sync::Mutex;
+use url::Url;
+
+use super::night_plan::{JiraUser, TestCycleStatus};
+use crate::{client::client::RolexClient, site::site::Site};
+
+/// One page of a paginated Zephyr Scale listing.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Page<T> {
+    next: Option<String>,
+    #[serde(rename = "startAt")]
+    start_at: usize,
+    #[serde(rename = "maxResults")]
+    max_results: usize,
+    total: Option<usize>,
+    #[serde(rename = "isLast")]
+    is_last: bool,
+    values: Vec<T>,
+}
+
+pub(crate) const MAX_RESULTS: usize = 100;
+
+/// Link to another Zephyr Scale or Jira resource, as `{id, self}`.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq)]
+pub struct Reference {
+    pub(crate) id: usize,
+    #[serde(rename = "self")]
+    pub(crate) url: String,
+}
+
+impl Reference {
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    pub fn get_url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// Test cycle statuses and Jira users already looked up, shared by the
+/// calls of one run so each is only fetched once.
+#[derive(Debug, Default)]
+pub struct ZephyrCache {
+    test_cycle_statuses: Mutex<HashMap<usize, TestCycleStatus>>,
+    jira_users: Mutex<HashMap<String, JiraUser>>,
+}
+
+impl ZephyrCache {
+    pub fn new() -> ZephyrCache {
+        ZephyrCache::default()
+    }
+}
+
+/// Walk every page of a Zephyr Scale listing starting at `url`.
+pub(crate) async fn get_all_pages<T: DeserializeOwned>(
+    client: &RolexClient,
+    url: Url,
+) -> Result<Vec<T>, Box<dyn Error>> {
+    let authorization = client.get_zephyr_authorization().await?;
+
+    let mut values = Vec::new();
+    let mut start_at = 0;
+
+    loop {
+        let mut page_url = url.clone();
+        page_url
+            .query_pairs_mut()
+            .append_pair("startAt", &start_at.to_string())
+            .append_pair("maxResults", &MAX_RESULTS.to_string());
+
+        let page: Page<T> = client
+            .send_json(client.get_http().get(page_url), Some(authorization), None)
+            .await?;
+        let received = page.values.len();
+        values.extend(page.values);
+
+        let is_done = page.is_last
+            || page.next.is_none()
+            || received == 0
+            || page.total.is_some_and(|total| values.len() >= total);
+        if is_done {
+            break;
+        }
+        start_at = page.start_at + page.max_results.max(received);
+    }
+    Ok(values)
+}
+
+/// Resolve a Zephyr Scale status, going through `cache`.
+pub(crate) async fn resolve_status(
+    client: &RolexClient,
+    cache: &ZephyrCache,
+    id: usize,
+    url: &str,
+) -> Result<TestCycleStatus, Box<dyn Error>> {
+    if let Some(status) = cache.test_cycle_statuses.lock().await.get(&id) {
+        return Ok(status.clone());
+    }
+
+    let authorization = client.get_zephyr_authorization().await?;
+    let status: TestCycleStatus = client
+        .send_json(client.get_http().get(url), Some(authorization), None)
+        .await?;
+    cache
+        .test_cycle_statuses
+        .lock()
+        .await
+        .insert(status.get_id(), status.clone());
+
+    Ok(status)
+}
+
+/// Resolve a Jira Cloud user, going through `cache`.
+pub(crate) async fn resolve_jira_user(
+    client: &RolexClient,
+    cache: &ZephyrCache,
+    account_id: &str,
+    url: &str,
+) -> Result<JiraUser, Box<dyn Error>> {
+    if let Some(user) = cache.jira_users.lock().await.get(account_id) {
+        return Ok(user.clone());
+    }
+
+    let authorization = client.get_jira_authorization().await?;
+    let user: JiraUser = client
+        .send_json(client.get_http().get(url), Some(authorization), None)
+        .await?;
+    cache
+        .jira_users
+        .lock()
+        .await
+        .insert(user.get_account_id().to_owned(), user.clone());
+
+    Ok(user)
+}
+
+/// Jira REST endpoint describing the user `account_id`.
+pub(crate) fn get_jira_user_url(site: &Site, account_id: &str) -> Result<Url, Box<dyn Error>> {
+    let mut url = Url::parse(site.get_jira_url())?.join("rest/api/2/user")?;
+    url.query_pairs_mut().append_pair("accountId", account_id);
+    Ok(url)
+}
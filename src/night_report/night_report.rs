@@ -0,0 +1,290 @@
+use chrono::Utc;
+use serde_json::json;
+use std::{collections::HashMap, error::Error};
+use thiserror::Error;
+use url::Url;
+
+use crate::{client::client::RolexClient, site::site::Site};
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorRetrievingNightReport(String);
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct NightReport {
+    id: String,
+    site_id: String,
+    telescope: Option<String>,
+    day_obs: usize,
+    summary: String,
+    #[serde(default)]
+    weather: Option<String>,
+    #[serde(default)]
+    maintel_summary: Option<String>,
+    #[serde(default)]
+    auxtel_summary: Option<String>,
+    telescope_status: Option<String>,
+    confluence_url: Option<String>,
+    #[serde(default)]
+    observers_crew: Vec<String>,
+    #[serde(default)]
+    urls: Vec<String>,
+    user_id: String,
+    user_agent: String,
+    is_valid: bool,
+    date_added: String,
+    date_sent: Option<String>,
+    date_invalidated: Option<String>,
+    parent_id: Option<String>,
+}
+
+/// Fields of a night report to create or update. Fields left as `None`
+/// are not sent, so an update only touches what was set.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct NightReportFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    day_obs: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    telescope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weather: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    maintel_summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auxtel_summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    telescope_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confluence_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    observers_crew: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    urls: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_agent: Option<String>,
+}
+
+impl NightReportFields {
+    pub fn new() -> NightReportFields {
+        NightReportFields {
+            user_agent: Some("rolex".to_owned()),
+            ..NightReportFields::default()
+        }
+    }
+
+    pub fn with_day_obs(mut self, day_obs: usize) -> NightReportFields {
+        self.day_obs = Some(day_obs);
+        self
+    }
+
+    pub fn with_telescope(mut self, telescope: &str) -> NightReportFields {
+        self.telescope = Some(telescope.to_owned());
+        self
+    }
+
+    pub fn with_summary(mut self, summary: &str) -> NightReportFields {
+        self.summary = Some(summary.to_owned());
+        self
+    }
+
+    pub fn with_weather(mut self, weather: &str) -> NightReportFields {
+        self.weather = Some(weather.to_owned());
+        self
+    }
+
+    pub fn with_maintel_summary(mut self, maintel_summary: &str) -> NightReportFields {
+        self.maintel_summary = Some(maintel_summary.to_owned());
+        self
+    }
+
+    pub fn with_auxtel_summary(mut self, auxtel_summary: &str) -> NightReportFields {
+        self.auxtel_summary = Some(auxtel_summary.to_owned());
+        self
+    }
+
+    pub fn with_telescope_status(mut self, telescope_status: &str) -> NightReportFields {
+        self.telescope_status = Some(telescope_status.to_owned());
+        self
+    }
+
+    pub fn with_confluence_url(mut self, confluence_url: &str) -> NightReportFields {
+        self.confluence_url = Some(confluence_url.to_owned());
+        self
+    }
+
+    pub fn with_observers_crew(mut self, observers_crew: &[String]) -> NightReportFields {
+        self.observers_crew = Some(observers_crew.to_vec());
+        self
+    }
+
+    pub fn with_urls(mut self, urls: &[String]) -> NightReportFields {
+        self.urls = Some(urls.to_vec());
+        self
+    }
+
+    pub fn with_user_id(mut self, user_id: &str) -> NightReportFields {
+        self.user_id = Some(user_id.to_owned());
+        self
+    }
+}
+
+impl NightReport {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_day_obs(&self) -> usize {
+        self.day_obs
+    }
+
+    pub fn get_summary(&self) -> &str {
+        &self.summary
+    }
+
+    pub fn get_weather(&self) -> &Option<String> {
+        &self.weather
+    }
+
+    pub fn get_maintel_summary(&self) -> &Option<String> {
+        &self.maintel_summary
+    }
+
+    pub fn get_auxtel_summary(&self) -> &Option<String> {
+        &self.auxtel_summary
+    }
+
+    pub fn get_observers_crew(&self) -> &[String] {
+        &self.observers_crew
+    }
+
+    pub fn get_urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    pub fn get_date_sent(&self) -> &Option<String> {
+        &self.date_sent
+    }
+
+    pub fn is_published(&self) -> bool {
+        self.date_sent.is_some()
+    }
+
+    pub async fn retrieve(
+        client: &RolexClient,
+        site: &Site,
+        params: &Option<HashMap<String, String>>,
+    ) -> Result<Vec<NightReport>, Box<dyn Error>> {
+        let url = {
+            let mut url = Url::parse(site.get_night_report_url())?;
+
+            if let Some(params) = params {
+                for (key, value) in params {
+                    url.query_pairs_mut().append_pair(key, value);
+                }
+            }
+            url
+        };
+
+        let response = client.send(client.get_http().get(url)).await?;
+
+        let response_text = response.text().await?;
+
+        let night_reports: Vec<NightReport> = serde_json::from_str(&response_text)?;
+
+        Ok(night_reports)
+    }
+
+    pub async fn create(
+        client: &RolexClient,
+        site: &Site,
+        fields: &NightReportFields,
+    ) -> Result<NightReport, Box<dyn Error>> {
+        let url = Url::parse(site.get_night_report_url())?;
+        let request = client.get_http().post(url);
+
+        client
+            .send_json(request, None, Some(&serde_json::to_value(fields)?))
+            .await
+    }
+
+    pub async fn update(
+        client: &RolexClient,
+        site: &Site,
+        id: &str,
+        fields: &NightReportFields,
+    ) -> Result<NightReport, Box<dyn Error>> {
+        let request = client
+            .get_http()
+            .patch(NightReport::get_report_url(site, id)?);
+
+        client
+            .send_json(request, None, Some(&serde_json::to_value(fields)?))
+            .await
+    }
+
+    /// Mark the report as sent, making it part of the official record.
+    pub async fn publish(
+        client: &RolexClient,
+        site: &Site,
+        id: &str,
+    ) -> Result<NightReport, Box<dyn Error>> {
+        let request = client
+            .get_http()
+            .patch(NightReport::get_report_url(site, id)?);
+        let publish = json!({
+            "date_sent": Utc::now().format("%Y-%m-%dT%H:%M:%S%.6f").to_string(),
+        });
+
+        client.send_json(request, None, Some(&publish)).await
+    }
+
+    fn get_report_url(site: &Site, id: &str) -> Result<Url, Box<dyn Error>> {
+        let mut url = Url::parse(site.get_night_report_url())?;
+        url.path_segments_mut()
+            .map_err(|_| ErrorRetrievingNightReport("Invalid night report url.".to_owned()))?
+            .pop_if_empty()
+            .push(id);
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let night_report_json = r#"{"id":"c5b2f8a4-41c7-4b5e-9a52-8b1f2e3a9d10","site_id":"summit","telescope":"Simonyi","day_obs":20240813,"summary":"Clear night, 312 science exposures.","weather":"Clear, seeing 0.8 arcsec.","telescope_status":"Parked at zenith.","confluence_url":null,"observers_crew":["observer1","observer2"],"user_id":"observer1","user_agent":"LOVE","is_valid":true,"date_added":"2024-08-14T10:00:00.000000","date_sent":null,"date_invalidated":null,"parent_id":null}"#;
+
+        let night_report: NightReport = serde_json::from_str(night_report_json).unwrap();
+
+        assert_eq!(night_report.get_day_obs(), 20240813);
+        assert_eq!(night_report.get_observers_crew().len(), 2);
+        assert_eq!(night_report.get_maintel_summary(), &None);
+        assert!(night_report.get_urls().is_empty());
+        assert!(!night_report.is_published());
+    }
+
+    #[test]
+    fn test_update_fields() {
+        let fields = NightReportFields::new()
+            .with_summary("Closed due to wind.")
+            .with_day_obs(20240813);
+
+        assert_eq!(
+            serde_json::to_string(&fields).unwrap(),
+            r#"{"day_obs":20240813,"summary":"Closed due to wind.","user_agent":"rolex"}"#
+        );
+        assert_eq!(
+            NightReport::get_report_url(&Site::summit(), "abc")
+                .unwrap()
+                .as_str(),
+            "https://summit-lsp.lsst.codes/nightreport/reports/abc"
+        );
+    }
+}
@@ -0,0 +1 @@
+pub mod night_report_page;
@@ -0,0 +1,178 @@
+use std::collections::{BTreeSet, HashMap};
+
+use askama::Template;
+
+use crate::{
+    night_fetcher::night_fetcher::{NightData, Source},
+    site::site::Site,
+    timeline::timeline::{build_timeline, Filterable, TimelineEntry},
+};
+
+/// Block of rendered entries of a single source.
+#[derive(Debug, Default)]
+pub struct Section {
+    id: String,
+    title: String,
+    status: String,
+    entries: Vec<String>,
+}
+
+/// Drop-down of the page filter bar, listing the values of one `data-*`
+/// attribute found in the night.
+#[derive(Debug, Default)]
+pub struct Filter {
+    attribute: String,
+    title: String,
+    values: Vec<String>,
+}
+
+const FILTERS: [(&str, &str); 8] = [
+    ("source", "Source"),
+    ("telescope", "Telescope"),
+    ("instrument", "Instrument"),
+    ("system", "System"),
+    ("component", "Component"),
+    ("severity", "Severity"),
+    ("user", "User"),
+    ("flag", "Exposure flag"),
+];
+
+/// Self-contained HTML document for a night, composing the fragment
+/// templates of every entry into per-source sections and a merged
+/// timeline. The stylesheet is embedded so the page can be archived as a
+/// single file.
+#[derive(Debug, Default, Template)]
+#[template(path = "night_report_page.html")]
+pub struct NightReportPage {
+    day_obs: String,
+    site: String,
+    night_plan: Option<String>,
+    filters: Vec<Filter>,
+    sections: Vec<Section>,
+    timeline: Vec<String>,
+}
+
+impl NightReportPage {
+    pub fn from_night_data(
+        site: &Site,
+        day_obs: usize,
+        night_data: &NightData,
+    ) -> askama::Result<NightReportPage> {
+        let night_plan = night_data
+            .get_night_plan()
+            .map(|night_plan| format!("{} - {}", night_plan.get_key(), night_plan.get_name()));
+
+        let timeline_entries = build_timeline(night_data);
+        let rendered = timeline_entries
+            .iter()
+            .map(|entry| Ok((entry.get_source(), entry.render_html()?)))
+            .collect::<askama::Result<Vec<(Source, String)>>>()?;
+
+        let sections = [
+            (Source::NarrativeLog, "Narrative log"),
+            (Source::ExposureLog, "Exposure log"),
+            (Source::FaultLog, "Alarms"),
+            (Source::BlockLog, "Blocks"),
+        ]
+        .into_iter()
+        .map(|(source, title)| Section {
+            id: format!("{source:?}"),
+            title: title.to_owned(),
+            status: night_data.describe_status(source),
+            entries: rendered
+                .iter()
+                .filter(|(entry_source, _)| *entry_source == source)
+                .map(|(_, html)| html.clone())
+                .collect(),
+        })
+        .collect();
+        let timeline = rendered.into_iter().map(|(_, html)| html).collect();
+
+        Ok(NightReportPage {
+            day_obs: day_obs.to_string(),
+            site: site.get_name().to_owned(),
+            night_plan,
+            filters: NightReportPage::build_filters(&timeline_entries),
+            sections,
+            timeline,
+        })
+    }
+
+    /// One filter per attribute present in the night, listing its distinct
+    /// values in order.
+    fn build_filters(timeline: &[TimelineEntry]) -> Vec<Filter> {
+        let mut values: HashMap<&str, BTreeSet<String>> = HashMap::new();
+        for entry in timeline {
+            for (attribute, value) in entry.get_filter_attributes() {
+                values.entry(attribute).or_default().extend(
+                    value
+                        .split('|')
+                        .filter(|value| !value.is_empty())
+                        .map(str::to_owned),
+                );
+            }
+        }
+
+        FILTERS
+            .iter()
+            .filter_map(|(attribute, title)| {
+                let values = values.remove(attribute)?;
+                Some(Filter {
+                    attribute: attribute.to_string(),
+                    title: title.to_string(),
+                    values: values.into_iter().collect(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{block_log::block_log::BlockLog, fault_log::fault_log::FaultLog};
+
+    #[test]
+    fn test_render_empty_night() {
+        let page =
+            NightReportPage::from_night_data(&Site::summit(), 20240813, &NightData::default())
+                .unwrap()
+                .render()
+                .unwrap();
+
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<title>Night report 20240813 - summit</title>"));
+        assert!(page.contains("<style>"));
+        assert!(page.contains(r#"<section id="NarrativeLog""#));
+        assert!(page.contains("not fetched"));
+    }
+
+    #[test]
+    fn test_build_filters() {
+        let fault_log: FaultLog = serde_json::from_str(
+            r#"{"name":"Enabled.ATDome","severity":3,"reason":"Fault","time":"2024-08-14T02:00:00Z"}"#,
+        )
+        .unwrap();
+        let block_log: BlockLog = serde_json::from_str(
+            r#"{"time":"2024-08-14T01:00:00Z","id":"BLOCK-T17","status":"COMPLETED","hash":"abc","sal_index":2}"#,
+        )
+        .unwrap();
+        let timeline = [
+            TimelineEntry::FaultLog(&fault_log),
+            TimelineEntry::BlockLog(&block_log),
+        ];
+
+        let filters = NightReportPage::build_filters(&timeline);
+        let html = fault_log.render().unwrap();
+
+        assert_eq!(filters.len(), 4);
+        assert_eq!(filters[0].attribute, "source");
+        assert_eq!(filters[0].values, vec!["BlockLog", "FaultLog"]);
+        assert_eq!(filters[1].values, vec!["AuxTel"]);
+        assert_eq!(filters[2].values, vec!["Enabled.ATDome"]);
+        assert!(html.contains(
+            r#"data-source="FaultLog" data-component="Enabled.ATDome" data-severity="3""#
+        ));
+    }
+}
@@ -0,0 +1,380 @@
+use crate::{
+    fault_log::alarm_episode::AlarmEpisode,
+    night_fetcher::night_fetcher::{NightData, Source},
+    site::site::Site,
+    timeline::timeline::format_time,
+};
+
+/// Terminal width used when none is configured.
+const DEFAULT_WIDTH: usize = 80;
+
+/// Narrowest column a table is shrunk to before it overflows.
+const MIN_COLUMN_WIDTH: usize = 10;
+
+/// Output independent building block of a summary.
+#[derive(Clone, Debug, PartialEq)]
+enum Block {
+    Heading(usize, String),
+    Note(String),
+    Bullets(Vec<String>),
+    Table(Vec<String>, Vec<Vec<String>>),
+}
+
+/// Night summary for pasting into chat and tickets, rendered either as
+/// Markdown or as plain text wrapped to a terminal width.
+#[derive(Debug)]
+pub struct NightSummary<'a> {
+    site: &'a Site,
+    day_obs: usize,
+    night_data: &'a NightData,
+    width: usize,
+}
+
+/// Greedy word wrap of every line of `text`, prefixing the first output
+/// line with `first_prefix` and the others with `prefix`. Words longer than
+/// the width are kept whole.
+fn wrap(text: &str, width: usize, first_prefix: &str, prefix: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = first_prefix.to_owned();
+    let mut is_empty = true;
+
+    for (index, text_line) in text.lines().enumerate() {
+        if index > 0 {
+            lines.push(line);
+            line = prefix.to_owned();
+            is_empty = true;
+        }
+        for word in text_line.split_whitespace() {
+            let length = line.chars().count() + word.chars().count();
+            if !is_empty && length + 1 > width {
+                lines.push(line);
+                line = prefix.to_owned();
+                is_empty = true;
+            }
+            if !is_empty {
+                line.push(' ');
+            }
+            line.push_str(word);
+            is_empty = false;
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+/// Escape the characters Markdown gives a meaning to, so that log messages
+/// show as they were typed.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        if matches!(
+            character,
+            '\\' | '`' | '*' | '_' | '#' | '[' | ']' | '<' | '>' | '|'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!(
+        "{text}{}",
+        " ".repeat(width.saturating_sub(text.chars().count()))
+    )
+}
+
+impl<'a> NightSummary<'a> {
+    pub fn new(site: &'a Site, day_obs: usize, night_data: &'a NightData) -> NightSummary<'a> {
+        NightSummary {
+            site,
+            day_obs,
+            night_data,
+            width: DEFAULT_WIDTH,
+        }
+    }
+
+    /// Wrap the plain-text output to `width` columns.
+    pub fn with_width(mut self, width: usize) -> NightSummary<'a> {
+        self.width = width;
+        self
+    }
+
+    pub fn to_markdown(&self) -> String {
+        NightSummary::render_markdown(self.get_blocks())
+    }
+
+    fn render_markdown(blocks: Vec<Block>) -> String {
+        let mut markdown = String::new();
+
+        for block in blocks {
+            match block {
+                Block::Heading(level, text) => {
+                    markdown.push_str(&format!("{} {text}\n\n", "#".repeat(level)))
+                }
+                Block::Note(text) => markdown.push_str(&format!("_{text}_\n\n")),
+                Block::Bullets(items) => {
+                    for item in items {
+                        markdown.push_str(&format!(
+                            "- {}\n",
+                            escape_markdown(&item).replace('\n', "\n  ")
+                        ));
+                    }
+                    markdown.push('\n');
+                }
+                Block::Table(headers, rows) => {
+                    let escape = |cell: &String| cell.replace('|', "\\|").replace('\n', " ");
+                    let row = |cells: &[String]| {
+                        let cells: Vec<String> = cells.iter().map(escape).collect();
+                        format!("| {} |\n", cells.join(" | "))
+                    };
+                    markdown.push_str(&row(&headers));
+                    markdown.push_str(&row(&vec!["---".to_owned(); headers.len()]));
+                    for cells in rows {
+                        markdown.push_str(&row(&cells));
+                    }
+                    markdown.push('\n');
+                }
+            }
+        }
+        markdown.trim_end().to_owned() + "\n"
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+
+        for block in self.get_blocks() {
+            match block {
+                Block::Heading(level, text) => {
+                    let underline = if level == 1 { "=" } else { "-" };
+                    lines.push(text.to_owned());
+                    lines.push(underline.repeat(text.chars().count().min(self.width)));
+                }
+                Block::Note(text) => lines.extend(wrap(&text, self.width, "", "")),
+                Block::Bullets(items) => {
+                    for item in items {
+                        lines.extend(wrap(&item, self.width, "- ", "  "));
+                    }
+                }
+                Block::Table(headers, rows) => lines.extend(self.format_table(&headers, &rows)),
+            }
+            lines.push(String::new());
+        }
+        lines.join("\n").trim_end().to_owned() + "\n"
+    }
+
+    /// Align the columns of a table, wrapping the last column when the
+    /// table is wider than the terminal.
+    fn format_table(&self, headers: &[String], rows: &[Vec<String>]) -> Vec<String> {
+        let mut widths: Vec<usize> = headers
+            .iter()
+            .map(|header| header.chars().count())
+            .collect();
+        for cells in rows {
+            for (width, cell) in widths.iter_mut().zip(cells) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let last = widths.len() - 1;
+        let used: usize = widths[..last].iter().map(|width| width + 2).sum();
+        widths[last] = widths[last].min(self.width.saturating_sub(used).max(MIN_COLUMN_WIDTH));
+
+        let format_row = |cells: &[String]| -> Vec<String> {
+            let prefix: String = cells[..last]
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| pad(cell, width + 2))
+                .collect();
+            let blank = " ".repeat(prefix.chars().count());
+            wrap(&cells[last], widths[last], "", "")
+                .into_iter()
+                .enumerate()
+                .map(|(index, line)| {
+                    let prefix = if index == 0 { &prefix } else { &blank };
+                    format!("{prefix}{line}").trim_end().to_owned()
+                })
+                .collect()
+        };
+
+        let mut lines = format_row(headers);
+        let separators: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        lines.push(separators.join("  "));
+        for cells in rows {
+            lines.extend(format_row(cells));
+        }
+        lines
+    }
+
+    fn get_blocks(&self) -> Vec<Block> {
+        let night_data = self.night_data;
+        let mut blocks = vec![Block::Heading(
+            1,
+            format!("Night summary {} - {}", self.day_obs, self.site.get_name()),
+        )];
+        if let Some(night_plan) = night_data.get_night_plan() {
+            blocks.push(Block::Note(format!(
+                "Night plan: {} - {}",
+                night_plan.get_key(),
+                night_plan.get_name()
+            )));
+        }
+
+        blocks.push(Block::Heading(2, "Narrative log".to_owned()));
+        blocks.push(Block::Note(
+            night_data.describe_status(Source::NarrativeLog),
+        ));
+        let items: Vec<String> = night_data
+            .get_narrative_logs()
+            .iter()
+            .map(|narrative_log| {
+                let mut item = format!(
+                    "{} {}: {}",
+                    format_time(narrative_log.get_date_added()),
+                    narrative_log.get_user_id(),
+                    narrative_log.get_message_plain_text()
+                );
+                if narrative_log.get_time_lost() > 0.0 {
+                    item.push_str(&format!(
+                        " (time lost: {:.2} h)",
+                        narrative_log.get_time_lost()
+                    ));
+                }
+                item
+            })
+            .collect();
+        if !items.is_empty() {
+            blocks.push(Block::Bullets(items));
+        }
+
+        blocks.push(Block::Heading(2, "Exposure log".to_owned()));
+        blocks.push(Block::Note(night_data.describe_status(Source::ExposureLog)));
+        let items: Vec<String> = night_data
+            .get_exposure_logs()
+            .iter()
+            .map(|exposure_log| {
+                let flag = match exposure_log.get_exposure_flag() {
+                    "" | "none" => String::new(),
+                    flag => format!(" [{flag}]"),
+                };
+                format!(
+                    "{}{flag} {}: {}",
+                    exposure_log.get_obs_id(),
+                    exposure_log.get_user_id(),
+                    exposure_log.get_message_plain_text()
+                )
+            })
+            .collect();
+        if !items.is_empty() {
+            blocks.push(Block::Bullets(items));
+        }
+
+        blocks.push(Block::Heading(2, "Alarms".to_owned()));
+        blocks.push(Block::Note(night_data.describe_status(Source::FaultLog)));
+        let rows: Vec<Vec<String>> = AlarmEpisode::from_fault_logs(night_data.get_fault_logs())
+            .iter()
+            .map(|episode| {
+                vec![
+                    format_time(episode.get_start()),
+                    episode
+                        .get_end()
+                        .as_deref()
+                        .map_or("ongoing".to_owned(), format_time),
+                    episode.get_name().to_owned(),
+                    episode.get_max_severity().to_string(),
+                    episode.get_count().to_string(),
+                    episode.get_reason().to_owned(),
+                ]
+            })
+            .collect();
+        if !rows.is_empty() {
+            let headers = ["Start", "End", "Alarm", "Severity", "Events", "Reason"];
+            blocks.push(Block::Table(
+                headers.iter().map(|header| header.to_string()).collect(),
+                rows,
+            ));
+        }
+
+        blocks.push(Block::Heading(2, "Blocks".to_owned()));
+        blocks.push(Block::Note(night_data.describe_status(Source::BlockLog)));
+        let rows: Vec<Vec<String>> = night_data
+            .get_block_logs()
+            .iter()
+            .map(|block_log| {
+                vec![
+                    format_time(block_log.get_date_added()),
+                    block_log.get_index_label(),
+                    block_log.get_status().to_owned(),
+                    block_log.get_id().to_owned(),
+                ]
+            })
+            .collect();
+        if !rows.is_empty() {
+            let headers = ["Time", "Telescope", "Status", "Block"];
+            blocks.push(Block::Table(
+                headers.iter().map(|header| header.to_string()).collect(),
+                rows,
+            ));
+        }
+
+        blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_format_table() {
+        let site = Site::summit();
+        let night_data = NightData::default();
+        let summary = NightSummary::new(&site, 20240813, &night_data).with_width(24);
+        let headers = vec!["Alarm".to_owned(), "Reason".to_owned()];
+        let rows = vec![vec![
+            "ATDome".to_owned(),
+            "Dome is not responding again".to_owned(),
+        ]];
+
+        assert_eq!(
+            summary.format_table(&headers, &rows),
+            vec![
+                "Alarm   Reason",
+                "------  ----------------",
+                "ATDome  Dome is not",
+                "        responding again",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_empty_night() {
+        let site = Site::summit();
+        let night_data = NightData::default();
+        let summary = NightSummary::new(&site, 20240813, &night_data);
+
+        let markdown = summary.to_markdown();
+        let text = summary.with_width(40).to_text();
+
+        assert!(markdown.starts_with(
+            "# Night summary 20240813 - summit\n\n## Narrative log\n\n_not fetched_\n"
+        ));
+        assert!(text.starts_with(
+            "Night summary 20240813 - summit\n===============================\n\nNarrative log\n-------------\n\nnot fetched\n"
+        ));
+    }
+
+    #[test]
+    fn test_escape_bullets() {
+        let markdown = NightSummary::render_markdown(vec![Block::Bullets(vec![
+            "02:00:00 observer: M1M3 *raised* with force_balance errors".to_owned(),
+        ])]);
+
+        assert_eq!(
+            markdown,
+            "- 02:00:00 observer: M1M3 \\*raised\\* with force\\_balance errors\n"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, error::Error, fs, path::Path};
+use thiserror::Error;
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorLoadingSite(String);
+
+/// Connection profile for one Rubin site.
+///
+/// Holds the EFD instance name and the base URLs of every remote service
+/// `rolex` talks to, so the retrieve functions do not need literals.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Site {
+    name: String,
+    efd_name: String,
+    narrative_log_url: String,
+    exposure_log_url: String,
+    #[serde(default)]
+    night_report_url: String,
+    zephyr_url: String,
+    jira_url: String,
+    #[serde(default = "default_confluence_url")]
+    confluence_url: String,
+    rubintv_url: String,
+    #[serde(default = "default_jira_projects")]
+    jira_projects: Vec<String>,
+    #[serde(default)]
+    email_recipients: Vec<String>,
+}
+
+const ZEPHYR_URL: &str = "https://api.zephyrscale.smartbear.com/v2/";
+const JIRA_URL: &str = "https://rubinobs.atlassian.net/";
+const CONFLUENCE_URL: &str = "https://rubinobs.atlassian.net/wiki/";
+const RUBINTV_URL: &str = "https://storage.googleapis.com/rubintv_data/";
+const JIRA_PROJECTS: [&str; 6] = ["OBS", "SITCOM", "BLOCK", "LOVE", "PREOPS", "DM"];
+
+fn default_confluence_url() -> String {
+    CONFLUENCE_URL.to_owned()
+}
+
+/// Night report URL on the host serving the narrative log, for profiles
+/// that do not set one.
+fn derive_night_report_url(narrative_log_url: &str) -> Option<String> {
+    narrative_log_url
+        .strip_suffix("narrativelog/messages")
+        .map(|base| format!("{base}nightreport/reports"))
+}
+
+fn default_jira_projects() -> Vec<String> {
+    JIRA_PROJECTS
+        .iter()
+        .map(|project| project.to_string())
+        .collect()
+}
+
+impl Site {
+    fn from_host(name: &str, efd_name: &str, host: &str) -> Site {
+        Site {
+            name: name.to_owned(),
+            efd_name: efd_name.to_owned(),
+            narrative_log_url: format!("https://{host}/narrativelog/messages"),
+            exposure_log_url: format!("https://{host}/exposurelog/messages"),
+            night_report_url: format!("https://{host}/nightreport/reports"),
+            zephyr_url: ZEPHYR_URL.to_owned(),
+            jira_url: JIRA_URL.to_owned(),
+            confluence_url: default_confluence_url(),
+            rubintv_url: RUBINTV_URL.to_owned(),
+            jira_projects: default_jira_projects(),
+            email_recipients: Vec::new(),
+        }
+    }
+
+    pub fn summit() -> Site {
+        Site::from_host("summit", "summit_efd", "summit-lsp.lsst.codes")
+    }
+
+    pub fn base() -> Site {
+        Site::from_host("base", "base_efd", "base-lsp.lsst.codes")
+    }
+
+    pub fn tucson() -> Site {
+        Site::from_host(
+            "tucson",
+            "tucson_teststand_efd",
+            "tucson-teststand.lsst.codes",
+        )
+    }
+
+    pub fn usdf() -> Site {
+        Site::from_host("usdf", "usdf_efd", "usdf-rsp.slac.stanford.edu")
+    }
+
+    /// Return one of the built-in profiles by name.
+    pub fn from_name(name: &str) -> Result<Site, Box<dyn Error>> {
+        match name {
+            "summit" => Ok(Site::summit()),
+            "base" => Ok(Site::base()),
+            "tucson" => Ok(Site::tucson()),
+            "usdf" => Ok(Site::usdf()),
+            _ => Err(Box::new(ErrorLoadingSite(format!(
+                "Unknown site profile: {name}"
+            )))),
+        }
+    }
+
+    /// Parse a config document mapping profile names to profiles.
+    pub fn load_all_from_str(text: &str) -> Result<HashMap<String, Site>, Box<dyn Error>> {
+        let mut sites: HashMap<String, Site> = serde_json::from_str(text)?;
+        for (name, site) in sites.iter_mut() {
+            if site.night_report_url.is_empty() {
+                site.night_report_url = derive_night_report_url(&site.narrative_log_url)
+                    .ok_or_else(|| {
+                        ErrorLoadingSite(format!(
+                            "Site profile {name} has no night_report_url and none can be derived from {}",
+                            site.narrative_log_url
+                        ))
+                    })?;
+            }
+        }
+        Ok(sites)
+    }
+
+    /// Load the profile `name` from a JSON config file.
+    pub fn load(path: &Path, name: &str) -> Result<Site, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        let mut sites = Site::load_all_from_str(&text)?;
+
+        sites.remove(name).ok_or_else(|| {
+            Box::new(ErrorLoadingSite(format!(
+                "Site profile {name} not found in {}",
+                path.display()
+            ))) as Box<dyn Error>
+        })
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_efd_name(&self) -> &str {
+        &self.efd_name
+    }
+
+    pub fn get_narrative_log_url(&self) -> &str {
+        &self.narrative_log_url
+    }
+
+    pub fn get_exposure_log_url(&self) -> &str {
+        &self.exposure_log_url
+    }
+
+    pub fn get_night_report_url(&self) -> &str {
+        &self.night_report_url
+    }
+
+    pub fn get_zephyr_url(&self) -> &str {
+        &self.zephyr_url
+    }
+
+    pub fn get_jira_url(&self) -> &str {
+        &self.jira_url
+    }
+
+    pub fn get_confluence_url(&self) -> &str {
+        &self.confluence_url
+    }
+
+    pub fn get_rubintv_url(&self) -> &str {
+        &self.rubintv_url
+    }
+
+    /// Jira projects whose issue keys are picked up from log messages.
+    pub fn get_jira_projects(&self) -> &[String] {
+        &self.jira_projects
+    }
+
+    /// Addresses the night report is emailed to.
+    pub fn get_email_recipients(&self) -> &[String] {
+        &self.email_recipients
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        let site = Site::from_name("base").unwrap();
+
+        assert_eq!(site.get_efd_name(), "base_efd");
+        assert_eq!(
+            site.get_narrative_log_url(),
+            "https://base-lsp.lsst.codes/narrativelog/messages"
+        );
+        assert!(Site::from_name("moon").is_err());
+    }
+
+    #[test]
+    fn test_load_all_from_str() {
+        let config = r#"{"tts":{"name":"tts","efd_name":"tucson_teststand_efd","narrative_log_url":"https://tucson-teststand.lsst.codes/narrativelog/messages","exposure_log_url":"https://tucson-teststand.lsst.codes/exposurelog/messages","zephyr_url":"https://api.zephyrscale.smartbear.com/v2/","jira_url":"https://rubinobs.atlassian.net/","rubintv_url":"https://storage.googleapis.com/rubintv_data/"}}"#;
+
+        let sites = Site::load_all_from_str(config).unwrap();
+
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites["tts"].get_name(), "tts");
+        assert_eq!(sites["tts"].get_efd_name(), "tucson_teststand_efd");
+        assert_eq!(
+            sites["tts"].get_exposure_log_url(),
+            Site::tucson().get_exposure_log_url()
+        );
+        assert_eq!(
+            sites["tts"].get_night_report_url(),
+            Site::tucson().get_night_report_url()
+        );
+    }
+}
@@ -0,0 +1,2 @@
+pub mod slack_digest;
+pub mod webhook;
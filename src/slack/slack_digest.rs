@@ -0,0 +1,273 @@
+use serde_json::{json, Value};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+};
+
+use crate::{
+    block_log::block_execution::BlockExecution,
+    fault_log::alarm_episode::AlarmEpisode,
+    night_fetcher::night_fetcher::{NightData, Source},
+    site::site::Site,
+    timeline::timeline::format_time,
+};
+
+/// Slack limit on the number of blocks of a message.
+const MAX_BLOCKS: usize = 50;
+
+/// Slack limit on the text of a section block.
+const MAX_TEXT_LENGTH: usize = 3000;
+
+/// Number of alarms listed in the digest.
+const TOP_ALARMS: usize = 5;
+
+/// Escape the characters Slack `mrkdwn` gives a meaning to.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Section blocks for `lines`, starting a new section whenever the text
+/// would exceed the Slack limit.
+fn sections(lines: &[String]) -> Vec<Value> {
+    let mut sections = Vec::new();
+    let mut text = String::new();
+
+    for line in lines {
+        let line: String = line.chars().take(MAX_TEXT_LENGTH).collect();
+        if !text.is_empty() && text.chars().count() + line.chars().count() + 1 > MAX_TEXT_LENGTH {
+            sections.push(json!({"type": "section", "text": {"type": "mrkdwn", "text": text}}));
+            text = String::new();
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&line);
+    }
+    if !text.is_empty() {
+        sections.push(json!({"type": "section", "text": {"type": "mrkdwn", "text": text}}));
+    }
+    sections
+}
+
+/// End-of-night digest in Slack Block Kit format.
+#[derive(Clone, Debug, Default)]
+pub struct SlackDigest {
+    title: String,
+    blocks: Vec<Value>,
+    max_blocks: usize,
+}
+
+impl SlackDigest {
+    pub fn from_night_data(site: &Site, day_obs: usize, night_data: &NightData) -> SlackDigest {
+        let title = format!("Night digest {day_obs} - {}", site.get_name());
+        let mut blocks = vec![json!({
+            "type": "header",
+            "text": {"type": "plain_text", "text": title},
+        })];
+
+        let mut context = Vec::new();
+        if let Some(night_plan) = night_data.get_night_plan() {
+            context.push(format!(
+                "Night plan {}",
+                escape(&format!(
+                    "{} - {}",
+                    night_plan.get_key(),
+                    night_plan.get_name()
+                ))
+            ));
+        }
+        for source in [
+            Source::NarrativeLog,
+            Source::ExposureLog,
+            Source::FaultLog,
+            Source::BlockLog,
+        ] {
+            context.push(escape(&format!(
+                "{source}: {}",
+                night_data.describe_status(source)
+            )));
+        }
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{"type": "mrkdwn", "text": context.join(" | ")}],
+        }));
+
+        blocks.push(json!({"type": "divider"}));
+        blocks.extend(sections(&SlackDigest::get_exposure_lines(night_data)));
+        blocks.extend(sections(&SlackDigest::get_block_lines(night_data)));
+        blocks.extend(sections(&SlackDigest::get_alarm_lines(night_data)));
+        blocks.extend(sections(&SlackDigest::get_time_lost_lines(night_data)));
+
+        SlackDigest {
+            title,
+            blocks,
+            max_blocks: MAX_BLOCKS,
+        }
+    }
+
+    /// Split into messages of at most `max_blocks` blocks.
+    pub fn with_max_blocks(mut self, max_blocks: usize) -> SlackDigest {
+        self.max_blocks = max_blocks.clamp(1, MAX_BLOCKS);
+        self
+    }
+
+    pub fn get_blocks(&self) -> &[Value] {
+        &self.blocks
+    }
+
+    /// Webhook payloads, the digest being split in as many messages as
+    /// needed to stay within the Slack block limit.
+    pub fn get_messages(&self) -> Vec<Value> {
+        let chunks: Vec<&[Value]> = self.blocks.chunks(self.max_blocks).collect();
+        let count = chunks.len();
+
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, blocks)| {
+                let text = if count > 1 {
+                    format!("{} ({}/{count})", self.title, index + 1)
+                } else {
+                    self.title.to_owned()
+                };
+                json!({"text": text, "blocks": blocks})
+            })
+            .collect()
+    }
+
+    /// Exposures commented on, counted once per obs_id under its flag.
+    fn get_exposure_lines(night_data: &NightData) -> Vec<String> {
+        let mut flags: HashMap<&str, &str> = HashMap::new();
+        for exposure_log in night_data.get_exposure_logs() {
+            flags.insert(exposure_log.get_obs_id(), exposure_log.get_exposure_flag());
+        }
+
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for flag in flags.into_values() {
+            *counts.entry(flag).or_default() += 1;
+        }
+
+        let counts: Vec<String> = counts
+            .iter()
+            .map(|(flag, count)| format!("{count} {}", escape(flag)))
+            .collect();
+        if counts.is_empty() {
+            return vec!["*Exposures:* none logged".to_owned()];
+        }
+        vec![format!("*Exposures:* {}", counts.join(", "))]
+    }
+
+    /// Block executions by their last status, failed ones listed.
+    fn get_block_lines(night_data: &NightData) -> Vec<String> {
+        let outcomes = BlockExecution::from_block_logs(night_data.get_block_logs());
+        let executed = outcomes
+            .iter()
+            .filter(|outcome| outcome.get_status() == "COMPLETED")
+            .count();
+        let failed: Vec<String> = outcomes
+            .iter()
+            .filter(|outcome| outcome.is_failed())
+            .map(|outcome| {
+                format!(
+                    "• {} {}",
+                    escape(outcome.get_id()),
+                    escape(outcome.get_status())
+                )
+            })
+            .collect();
+
+        let mut lines = vec![format!(
+            "*Blocks:* {executed} executed, {} failed",
+            failed.len()
+        )];
+        lines.extend(failed);
+        lines
+    }
+
+    /// Alarms by highest severity reached, then by number of episodes.
+    fn get_alarm_lines(night_data: &NightData) -> Vec<String> {
+        let mut alarms: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        let episodes = AlarmEpisode::from_fault_logs(night_data.get_fault_logs());
+        for episode in &episodes {
+            let alarm = alarms.entry(episode.get_name()).or_default();
+            alarm.0 = alarm.0.max(episode.get_max_severity());
+            alarm.1 += 1;
+        }
+
+        let mut alarms: Vec<(&str, (usize, usize))> = alarms.into_iter().collect();
+        alarms.sort_by_key(|(_, alarm)| Reverse(*alarm));
+
+        let mut lines = vec![format!("*Top alarms:* {} raised", alarms.len())];
+        lines.extend(
+            alarms
+                .iter()
+                .take(TOP_ALARMS)
+                .map(|(name, (severity, count))| {
+                    format!(
+                        "• `{}` severity {severity}, {count} episode(s)",
+                        escape(name)
+                    )
+                }),
+        );
+        lines
+    }
+
+    fn get_time_lost_lines(night_data: &NightData) -> Vec<String> {
+        let narrative_logs: Vec<_> = night_data
+            .get_narrative_logs()
+            .iter()
+            .filter(|narrative_log| narrative_log.get_time_lost() > 0.0)
+            .collect();
+        let total: f32 = narrative_logs
+            .iter()
+            .map(|narrative_log| narrative_log.get_time_lost())
+            .sum();
+
+        let mut lines = vec![format!("*Time lost:* {total:.2} h")];
+        lines.extend(narrative_logs.iter().map(|narrative_log| {
+            let plain_text = narrative_log.get_message_plain_text();
+            format!(
+                "• {} {}: {} ({:.2} h)",
+                format_time(narrative_log.get_date_added()),
+                escape(narrative_log.get_user_id()),
+                escape(plain_text.lines().next().unwrap_or_default()),
+                narrative_log.get_time_lost()
+            )
+        }));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_sections() {
+        let lines: Vec<String> = (0..4).map(|_| "a".repeat(1000)).collect();
+
+        let sections = sections(&lines);
+
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0]["text"]["text"].as_str().unwrap().len(), 2001);
+        assert_eq!(escape("<b> & </b>"), "&lt;b&gt; &amp; &lt;/b&gt;");
+    }
+
+    #[test]
+    fn test_get_messages() {
+        let digest = SlackDigest::from_night_data(&Site::summit(), 20240813, &NightData::default())
+            .with_max_blocks(3);
+
+        let messages = digest.get_messages();
+
+        assert_eq!(messages.len(), digest.get_blocks().len().div_ceil(3));
+        assert_eq!(messages[0]["blocks"][0]["type"], "header");
+        assert_eq!(
+            messages[1]["text"],
+            format!("Night digest 20240813 - summit (2/{})", messages.len())
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::error::Error;
+use thiserror::Error;
+use url::Url;
+
+use super::slack_digest::SlackDigest;
+use crate::{client::client::RolexClient, credentials::credentials::SLACK_WEBHOOK_URL};
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("{0}")]
+pub struct ErrorPostingToSlack(String);
+
+/// Slack incoming webhook of a channel.
+#[derive(Clone, Debug)]
+pub struct SlackWebhook {
+    url: Url,
+}
+
+impl SlackWebhook {
+    pub fn new(url: &str) -> Result<SlackWebhook, Box<dyn Error>> {
+        Ok(SlackWebhook {
+            url: Url::parse(url)?,
+        })
+    }
+
+    /// Webhook configured as the `SLACK_WEBHOOK_URL` credential, since the
+    /// url itself grants posting to the channel.
+    pub fn from_credentials(client: &RolexClient) -> Result<SlackWebhook, Box<dyn Error>> {
+        SlackWebhook::new(&client.get_credentials().get(SLACK_WEBHOOK_URL)?)
+    }
+
+    pub fn get_url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Post every message of `digest` in order, stopping at the first
+    /// rejected one.
+    pub async fn post(
+        &self,
+        client: &RolexClient,
+        digest: &SlackDigest,
+    ) -> Result<usize, Box<dyn Error>> {
+        let messages = digest.get_messages();
+
+        for message in &messages {
+            let request = client
+                .get_http()
+                .post(self.url.clone())
+                .header("Content-Type", "application/json")
+                .body(serde_json::to_string(message)?);
+            let response = client.send(request).await?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let response_text = response.text().await?;
+                return Err(Box::new(ErrorPostingToSlack(format!(
+                    "Error: {status} {response_text}"
+                ))));
+            }
+        }
+        Ok(messages.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{night_fetcher::night_fetcher::NightData, site::site::Site};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Read one HTTP request from a local stand-in and return its body.
+    async fn read_body(stream: &mut tokio::net::TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let content_length: usize = head
+                    .lines()
+                    .find_map(|line| {
+                        line.to_lowercase()
+                            .strip_prefix("content-length: ")
+                            .map(str::to_owned)
+                    })
+                    .and_then(|length| length.parse().ok())
+                    .unwrap_or(0);
+                if body.len() >= content_length {
+                    return body.to_owned();
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_to_local_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let webhook = SlackWebhook::new(&format!(
+            "http://{}/services/T/B/X",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let digest = SlackDigest::from_night_data(&Site::summit(), 20240813, &NightData::default())
+            .with_max_blocks(4);
+        let expected = digest.get_messages().len();
+
+        let server = tokio::spawn(async move {
+            let mut bodies = Vec::new();
+            for _ in 0..expected {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                bodies.push(read_body(&mut stream).await);
+                stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    )
+                    .await
+                    .unwrap();
+            }
+            bodies
+        });
+
+        let posted = webhook.post(&RolexClient::new(), &digest).await.unwrap();
+        let bodies = server.await.unwrap();
+
+        assert!(expected > 1);
+        assert_eq!(posted, expected);
+        let first: serde_json::Value = serde_json::from_str(&bodies[0]).unwrap();
+        assert_eq!(first["blocks"][0]["type"], "header");
+    }
+}
@@ -0,0 +1,136 @@
+use askama::Template;
+use chrono::{DateTime, NaiveDateTime};
+
+use crate::{
+    block_log::block_log::BlockLog,
+    exposure_log::exposure_log::ExposureLog,
+    fault_log::fault_log::FaultLog,
+    narrative_log::narrative_log::NarrativeLog,
+    night_fetcher::night_fetcher::{NightData, Source},
+};
+
+/// Parse the timestamps used by the log services (naive, UTC) and by
+/// InfluxDB (RFC 3339).
+pub fn parse_time(time: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%.f"))
+        .ok()
+}
+
+/// Time of day of a log timestamp, or the timestamp as is when it cannot
+/// be parsed.
+pub fn format_time(time: &str) -> String {
+    parse_time(time)
+        .map(|time| time.format("%H:%M:%S").to_string())
+        .unwrap_or(time.to_owned())
+}
+
+/// Entry the report page can filter on.
+pub trait Filterable {
+    /// Values of the entry as `data-*` attribute name and `|` separated
+    /// values. An attribute name has the same meaning for every source:
+    /// `telescope` is the SAL index a block ran on, `instrument` the camera
+    /// of an exposure and `system` the systems a narrative entry is about.
+    fn get_filter_attributes(&self) -> Vec<(&'static str, String)>;
+}
+
+/// One entry of any source, for views merging all sources in time order.
+#[derive(Clone, Copy, Debug)]
+pub enum TimelineEntry<'a> {
+    NarrativeLog(&'a NarrativeLog),
+    ExposureLog(&'a ExposureLog),
+    FaultLog(&'a FaultLog),
+    BlockLog(&'a BlockLog),
+}
+
+impl<'a> TimelineEntry<'a> {
+    pub fn get_source(&self) -> Source {
+        match self {
+            TimelineEntry::NarrativeLog(_) => Source::NarrativeLog,
+            TimelineEntry::ExposureLog(_) => Source::ExposureLog,
+            TimelineEntry::FaultLog(_) => Source::FaultLog,
+            TimelineEntry::BlockLog(_) => Source::BlockLog,
+        }
+    }
+
+    pub fn get_time_str(&self) -> Option<&'a str> {
+        match self {
+            TimelineEntry::NarrativeLog(entry) => Some(entry.get_date_added()),
+            TimelineEntry::ExposureLog(entry) => entry.get_date_added().as_deref(),
+            TimelineEntry::FaultLog(entry) => Some(entry.get_time()),
+            TimelineEntry::BlockLog(entry) => Some(entry.get_date_added()),
+        }
+    }
+
+    pub fn get_time(&self) -> Option<NaiveDateTime> {
+        self.get_time_str().and_then(parse_time)
+    }
+
+    /// Render the entry with its own fragment template.
+    pub fn render_html(&self) -> askama::Result<String> {
+        match self {
+            TimelineEntry::NarrativeLog(entry) => entry.render(),
+            TimelineEntry::ExposureLog(entry) => entry.render(),
+            TimelineEntry::FaultLog(entry) => entry.render(),
+            TimelineEntry::BlockLog(entry) => entry.render(),
+        }
+    }
+}
+
+impl Filterable for TimelineEntry<'_> {
+    fn get_filter_attributes(&self) -> Vec<(&'static str, String)> {
+        match self {
+            TimelineEntry::NarrativeLog(entry) => entry.get_filter_attributes(),
+            TimelineEntry::ExposureLog(entry) => entry.get_filter_attributes(),
+            TimelineEntry::FaultLog(entry) => entry.get_filter_attributes(),
+            TimelineEntry::BlockLog(entry) => entry.get_filter_attributes(),
+        }
+    }
+}
+
+/// Every entry of the night sorted by time, entries without a usable
+/// timestamp last.
+pub fn build_timeline(night_data: &NightData) -> Vec<TimelineEntry<'_>> {
+    let mut timeline: Vec<TimelineEntry> = night_data
+        .get_narrative_logs()
+        .iter()
+        .map(TimelineEntry::NarrativeLog)
+        .chain(
+            night_data
+                .get_exposure_logs()
+                .iter()
+                .map(TimelineEntry::ExposureLog),
+        )
+        .chain(
+            night_data
+                .get_fault_logs()
+                .iter()
+                .map(TimelineEntry::FaultLog),
+        )
+        .chain(
+            night_data
+                .get_block_logs()
+                .iter()
+                .map(TimelineEntry::BlockLog),
+        )
+        .collect();
+
+    timeline.sort_by_key(|entry| (entry.get_time().is_none(), entry.get_time()));
+    timeline
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_time() {
+        let narrative = parse_time("2024-08-14T02:00:00.169017").unwrap();
+        let influx = parse_time("2024-08-14T02:00:00.5Z").unwrap();
+
+        assert!(narrative < influx);
+        assert_eq!(parse_time("yesterday"), None);
+    }
+}